@@ -2,7 +2,11 @@
 //!
 //! 定义了执行外部命令时可能发生的错误类型。
 
+use crate::cmd::cmd_output::{CmdOutput, PipelineOutput};
+use crate::process::{SignalError, UserError};
 use std::io::Error;
+use std::time::Duration;
+use tracing_appender::rolling::InitError;
 
 /// # 命令执行错误枚举
 ///
@@ -18,25 +22,130 @@ pub enum CmdError {
     Execute(Error),
     /// 命令运行失败错误
     ///
-    /// 当命令被执行但返回非零退出码时返回此错误
-    /// 包含命令输出的错误信息
+    /// 当命令被执行但返回非零退出码或被信号终止时返回此错误
+    /// 包含完整的命令输出，可从中获取退出码、终止信号、标准输出和标准错误
     #[error("运行命令失败: {0}")]
-    Run(String),
+    Run(CmdOutput),
+    /// 管道命令运行失败错误
+    ///
+    /// 当管道中任意一个阶段返回非零退出码或被信号终止时返回此错误（`pipefail` 语义）
+    /// 包含每个阶段的命令输出
+    #[error("运行管道命令失败: {0}")]
+    Pipeline(PipelineOutput),
+    /// 等待命令进程退出失败错误
+    ///
+    /// 当无法获取命令进程的退出状态时返回此错误
+    /// 包装了底层的 [`Error`]
+    #[error("等待命令进程退出失败: {0}")]
+    Wait(Error),
     /// 杀死命令进程失败错误
     ///
     /// 当无法杀死命令进程时返回此错误
     /// 包装了底层的 [`Error`]
     #[error("杀死命令进程失败: {0}")]
     Kill(Error),
+    /// 发送信号失败错误
+    ///
+    /// 当信号指令无效或无法向命令进程发送信号时返回此错误
+    /// 包装了底层的 [`SignalError`]
+    #[error(transparent)]
+    Signal(#[from] SignalError),
     /// 获取命令输出失败错误
     ///
     /// 当无法获取命令输出时返回此错误
     /// 封装了底层的 [`String`]
     #[error("获取命令输出失败")]
     TakeStdout(),
+    /// 获取命令错误输出失败错误
+    ///
+    /// 当需要读取标准错误但无法获取命令的错误输出管道时返回此错误
+    #[error("获取命令错误输出失败")]
+    TakeStderr(),
+    /// 进程不存在错误
+    ///
+    /// 当向不存在的进程发送信号时返回此错误
+    #[error("进程不存在: {0}")]
+    NoSuchProcess(u32),
+    /// 权限不足错误
+    ///
+    /// 当没有权限向进程发送信号时返回此错误
+    #[error("没有权限向进程发送信号: {0}")]
+    PermissionDenied(u32),
     /// 进程ID为空错误
     ///
     /// 当进程ID为空时返回此错误
     #[error("进程ID为空")]
     EmptyId,
+    /// 进程ID无效错误
+    ///
+    /// 当进程ID为 0 或超过 `i32::MAX` 时返回此错误，这些值在 `kill(2)` 中表示调用者所在的进程组或所有进程
+    #[error("进程ID无效: {0}")]
+    InvalidPid(u32),
+    /// 命令运行超时错误
+    ///
+    /// 当命令运行时间超过设定的超时时间时返回此错误，此时子进程已被杀死
+    #[error("运行命令超时: {0:?}")]
+    Timeout(Duration),
+    /// 命令输出过大错误
+    ///
+    /// 当命令的标准输出或标准错误超过设定的大小上限时返回此错误，此时子进程已被杀死
+    #[error("命令输出超过大小上限: {0} 字节")]
+    OutputTooLarge(usize),
+    /// 写入标准输入失败错误
+    ///
+    /// 当向子进程的标准输入写入数据失败（如子进程已退出）时返回此错误
+    /// 包装了底层的 [`Error`]
+    #[error("写入命令标准输入失败: {0}")]
+    WriteStdin(Error),
+    /// 标准输入已关闭错误
+    ///
+    /// 当向已关闭的子进程标准输入写入数据时返回此错误
+    #[error("命令标准输入已关闭")]
+    StdinClosed,
+    /// 等待输出匹配超时错误
+    ///
+    /// 当超过超时时间仍未在命令输出中匹配到指定的正则表达式时返回此错误
+    #[error("等待命令输出匹配 {0} 超时: {1:?}")]
+    ExpectTimeout(String, Duration),
+    /// 输出结束仍未匹配错误
+    ///
+    /// 当命令输出结束时仍未匹配到指定的正则表达式时返回此错误
+    #[error("命令输出结束时仍未匹配 {0}")]
+    ExpectEof(String),
+    /// 伪终端操作失败错误
+    ///
+    /// 当打开伪终端、写入终端输入或调整窗口大小失败时返回此错误
+    /// 包装了底层的 [`Error`]
+    #[error("伪终端操作失败: {0}")]
+    Pty(Error),
+    /// 打开输出日志文件失败错误
+    ///
+    /// 当创建日志目录或打开轮转日志文件失败时返回此错误
+    /// 包装了底层的 [`InitError`]
+    #[error("打开命令输出日志文件失败")]
+    OutputLog(#[source] InitError),
+    /// 资源限制无效错误
+    ///
+    /// 当设置的资源限制中软限制大于硬限制时返回此错误
+    /// 只在启动命令前校验配置时返回，子进程中 `setrlimit` 失败（如权限不足）时返回 [`CmdError::Execute`]
+    #[error("资源限制无效: {0}")]
+    InvalidResourceLimit(String),
+    /// 进程优先级无效错误
+    ///
+    /// 当设置的进程优先级不在 -20 到 19 之间时返回此错误
+    /// 只在启动命令前校验配置时返回，子进程中 `setpriority` 失败（如权限不足）时返回 [`CmdError::Execute`]
+    #[error("进程优先级无效: {0}")]
+    InvalidNice(i32),
+    /// 文件权限掩码无效错误
+    ///
+    /// 当设置的文件权限掩码大于 `0o777` 时返回此错误
+    /// 只在启动命令前校验配置时返回
+    #[error("文件权限掩码无效: {0:o}")]
+    InvalidUmask(u32),
+    /// 切换运行身份失败错误
+    ///
+    /// 当无法解析子进程运行所使用的用户或用户组时返回此错误
+    /// 包装了底层的 [`UserError`]
+    #[error(transparent)]
+    User(#[from] UserError),
 }
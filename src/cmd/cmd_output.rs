@@ -0,0 +1,140 @@
+//! # 命令输出类型
+//!
+//! 定义了外部命令执行完成后的结构化结果，包括退出码、终止信号、标准输出、标准错误和运行时长，
+//! 以及管道命令中每个阶段的结果。
+
+use std::fmt::Display;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// # 命令输出
+///
+/// 外部命令执行完成后的结构化结果。无论命令成功与否都会完整保留输出内容，
+/// 以便调用者根据具体的退出码或终止信号进行处理。
+#[derive(Debug, Clone)]
+pub struct CmdOutput {
+    /// 进程退出码，进程被信号终止时为 `None`
+    pub code: Option<i32>,
+    /// 终止进程的信号编号，进程正常退出时为 `None`
+    pub signal: Option<i32>,
+    /// 标准输出内容
+    pub stdout: Vec<u8>,
+    /// 标准错误内容
+    pub stderr: Vec<u8>,
+    /// 命令运行时长
+    pub duration: Duration,
+}
+
+impl CmdOutput {
+    /// # 根据进程退出状态创建命令输出
+    pub(crate) fn new(
+        status: ExitStatus,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            code: status.code(),
+            signal: status.signal(),
+            stdout,
+            stderr,
+            duration,
+        }
+    }
+
+    /// # 命令是否执行成功
+    ///
+    /// 仅当进程正常退出且退出码为 0 时返回 `true`。
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// # 以字符串形式获取标准输出
+    ///
+    /// 非法的 UTF-8 字节序列会被替换为 `U+FFFD`。
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).to_string()
+    }
+
+    /// # 以字符串形式获取标准错误
+    ///
+    /// 非法的 UTF-8 字节序列会被替换为 `U+FFFD`。
+    pub fn stderr_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).to_string()
+    }
+}
+
+impl Display for CmdOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {code}")?,
+            (None, Some(signal)) => write!(f, "killed by signal {signal}")?,
+            (None, None) => write!(f, "unknown exit status")?,
+        }
+        let stderr = self.stderr_lossy();
+        let stderr = stderr.trim();
+        if !stderr.is_empty() {
+            write!(f, ": {stderr}")?;
+        }
+        Ok(())
+    }
+}
+
+/// # 管道命令输出
+///
+/// 管道命令执行完成后每个阶段的结果，顺序与管道中命令的顺序一致。
+/// 除最后一个阶段外，其余阶段的标准输出都写入了下一个阶段的标准输入，因此为空。
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    /// 每个阶段的命令输出
+    pub stages: Vec<CmdOutput>,
+}
+
+impl PipelineOutput {
+    /// # 管道命令是否执行成功
+    ///
+    /// 与 shell 的 `pipefail` 语义一致，仅当所有阶段都执行成功时返回 `true`。
+    pub fn success(&self) -> bool {
+        self.stages.iter().all(CmdOutput::success)
+    }
+
+    /// # 获取管道命令的标准输出
+    ///
+    /// 即最后一个阶段的标准输出。
+    pub fn stdout(&self) -> &[u8] {
+        self.stages
+            .last()
+            .map(|stage| stage.stdout.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// # 以字符串形式获取管道命令的标准输出
+    ///
+    /// 非法的 UTF-8 字节序列会被替换为 `U+FFFD`。
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(self.stdout()).to_string()
+    }
+
+    /// # 获取第一个执行失败的阶段
+    ///
+    /// 返回阶段的序号（从 0 开始）及其命令输出，所有阶段都执行成功时返回 `None`。
+    pub fn first_failure(&self) -> Option<(usize, &CmdOutput)> {
+        self.stages
+            .iter()
+            .enumerate()
+            .find(|(_, stage)| !stage.success())
+    }
+}
+
+impl Display for PipelineOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "stage {index}: {stage}")?;
+        }
+        Ok(())
+    }
+}
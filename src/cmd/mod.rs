@@ -6,7 +6,14 @@
 //! - 执行外部命令并获取输出
 //! - 检查进程是否存活
 //! - 杀死进程
+//! - 控制子进程的进程组和会话
+//! - 限制子进程的资源使用
+//! - 以指定的用户和用户组运行子进程
 
 pub mod cmd_error;
+pub mod cmd_output;
+pub mod process_group;
+pub(crate) mod resource_limits;
+pub(crate) mod run_as;
 pub mod std;
 pub mod spawn;
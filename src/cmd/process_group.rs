@@ -0,0 +1,53 @@
+//! # 进程组模块
+//!
+//! 提供 [ProcessGroup] 枚举，用于控制子进程所属的进程组和会话。
+//!
+//! 子进程成为新进程组的组长后，可以通过
+//! [kill_process_group](crate::cmd::std::cmd_utils::kill_process_group) 向整个进程组发送信号，
+//! 从而一并结束由其派生的所有后代进程（如 `sh -c "a | b"` 中的每个管道成员）。
+
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// # 子进程的进程组
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProcessGroup {
+    /// 继承父进程的进程组和会话
+    #[default]
+    Inherit,
+    /// 创建新的进程组，子进程成为组长，进程组ID等于子进程ID
+    NewGroup,
+    /// 创建新的会话，子进程成为会话首进程和新进程组的组长，并脱离控制终端
+    NewSession,
+}
+
+impl ProcessGroup {
+    /// # 子进程是否为新进程组的组长
+    ///
+    /// 为 `true` 时子进程ID即为进程组ID，可以向整个进程组发送信号。
+    pub fn is_leader(&self) -> bool {
+        !matches!(self, ProcessGroup::Inherit)
+    }
+
+    /// # 应用到命令上
+    pub(crate) fn apply(&self, command: &mut Command) {
+        match self {
+            ProcessGroup::Inherit => {}
+            ProcessGroup::NewGroup => {
+                command.process_group(0);
+            }
+            ProcessGroup::NewSession => {
+                // SAFETY: setsid 是异步信号安全的，闭包中不分配内存也不获取锁
+                unsafe {
+                    command.pre_exec(|| {
+                        if libc::setsid() == -1 {
+                            return Err(io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! # 资源限制模块
+//!
+//! 提供 [ResourceLimits] 结构体，用于在子进程执行目标程序之前设置资源限制（`setrlimit`）、
+//! 进程优先级（`nice`）和文件权限掩码（`umask`），适用于启动不受信任或资源消耗较大的辅助程序。
+
+use crate::cmd::cmd_error::CmdError;
+use nix::sys::resource::{Resource, setrlimit};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// 进程优先级的最小值（最高优先级）
+const MIN_NICE: i32 = -20;
+/// 进程优先级的最大值（最低优先级）
+const MAX_NICE: i32 = 19;
+/// 文件权限掩码的最大值
+const MAX_UMASK: u32 = 0o777;
+
+/// # 资源限制
+///
+/// 记录子进程的资源限制、进程优先级和文件权限掩码，在子进程中 `exec` 之前依次应用。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ResourceLimits {
+    /// 资源限制，每项为资源类型、软限制和硬限制
+    rlimits: Vec<(Resource, u64, u64)>,
+    /// 进程优先级
+    nice: Option<i32>,
+    /// 文件权限掩码
+    umask: Option<u32>,
+}
+
+impl ResourceLimits {
+    /// # 设置资源限制
+    ///
+    /// 同一资源多次设置时，以最后一次为准。
+    pub(crate) fn rlimit(&mut self, resource: Resource, soft: u64, hard: u64) {
+        self.rlimits.retain(|(r, _, _)| *r != resource);
+        self.rlimits.push((resource, soft, hard));
+    }
+
+    /// # 设置进程优先级
+    pub(crate) fn nice(&mut self, nice: i32) {
+        self.nice = Some(nice);
+    }
+
+    /// # 设置文件权限掩码
+    pub(crate) fn umask(&mut self, umask: u32) {
+        self.umask = Some(umask);
+    }
+
+    /// # 校验设置是否有效
+    ///
+    /// ## 错误处理
+    ///
+    /// * 软限制大于硬限制时，返回 [CmdError::InvalidResourceLimit] 错误。
+    /// * 进程优先级不在 -20 到 19 之间时，返回 [CmdError::InvalidNice] 错误。
+    /// * 文件权限掩码大于 `0o777` 时，返回 [CmdError::InvalidUmask] 错误。
+    pub(crate) fn validate(&self) -> Result<(), CmdError> {
+        for (resource, soft, hard) in &self.rlimits {
+            if soft > hard {
+                return Err(CmdError::InvalidResourceLimit(format!(
+                    "{resource:?} soft limit {soft} exceeds hard limit {hard}"
+                )));
+            }
+        }
+        if let Some(nice) = self.nice
+            && !(MIN_NICE..=MAX_NICE).contains(&nice)
+        {
+            return Err(CmdError::InvalidNice(nice));
+        }
+        if let Some(umask) = self.umask
+            && umask > MAX_UMASK
+        {
+            return Err(CmdError::InvalidUmask(umask));
+        }
+        Ok(())
+    }
+
+    /// # 应用到命令上
+    ///
+    /// 未设置任何限制时不会注册 `pre_exec` 回调。子进程中设置失败时，启动命令会失败，
+    /// 并返回对应的系统错误（如提高硬限制或优先级时权限不足返回 `EPERM`）。
+    pub(crate) fn apply(&self, command: &mut Command) {
+        if self.rlimits.is_empty() && self.nice.is_none() && self.umask.is_none() {
+            return;
+        }
+        let limits = self.clone();
+        // SAFETY: setrlimit、setpriority 和 umask 都是异步信号安全的，闭包中不分配内存也不获取锁
+        unsafe {
+            command.pre_exec(move || {
+                for (resource, soft, hard) in &limits.rlimits {
+                    setrlimit(*resource, *soft, *hard).map_err(io::Error::from)?;
+                }
+                if let Some(nice) = limits.nice
+                    && libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(umask) = limits.umask {
+                    libc::umask(umask as libc::mode_t);
+                }
+                Ok(())
+            });
+        }
+    }
+}
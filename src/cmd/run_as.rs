@@ -0,0 +1,93 @@
+//! # 运行身份模块
+//!
+//! 提供 [RunAs] 结构体，用于在子进程执行目标程序之前切换到指定的用户、用户组和附加用户组，
+//! 适用于以 root 身份运行的守护进程以非特权账户启动辅助程序。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::process::{IdOrName, resolve_credentials, resolve_gid, set_credentials};
+use nix::unistd::{Gid, Uid, setgid, setgroups};
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// # 运行身份
+///
+/// 记录子进程运行所使用的用户、用户组和附加用户组，名称在启动子进程之前解析。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunAs {
+    /// 用户
+    user: Option<IdOrName>,
+    /// 用户组
+    group: Option<IdOrName>,
+    /// 附加用户组
+    groups: Option<Vec<IdOrName>>,
+}
+
+impl RunAs {
+    /// # 设置用户
+    pub(crate) fn user(&mut self, user: IdOrName) {
+        self.user = Some(user);
+    }
+
+    /// # 设置用户组
+    pub(crate) fn group(&mut self, group: IdOrName) {
+        self.group = Some(group);
+    }
+
+    /// # 设置附加用户组
+    pub(crate) fn groups(&mut self, groups: Vec<IdOrName>) {
+        self.groups = Some(groups);
+    }
+
+    /// # 应用到命令上
+    ///
+    /// 解析用户和用户组，并注册在子进程中依次设置附加用户组、用户组ID和用户ID的 `pre_exec` 回调。
+    /// 只设置了用户组或附加用户组时，不切换用户。
+    ///
+    /// ## 错误处理
+    ///
+    /// 解析用户或用户组失败时，返回 [CmdError::User] 错误。
+    pub(crate) fn apply(&self, command: &mut Command) -> Result<(), CmdError> {
+        if let Some(user) = &self.user {
+            let credentials =
+                resolve_credentials(user, self.group.as_ref(), self.groups.as_deref())?;
+            let groups: Vec<Gid> = credentials.groups.into_iter().map(Gid::from_raw).collect();
+            let gid = Gid::from_raw(credentials.gid);
+            let uid = Uid::from_raw(credentials.uid);
+            // SAFETY: setgroups、setgid 和 setuid 都是异步信号安全的，闭包中不分配内存也不获取锁
+            unsafe {
+                command
+                    .pre_exec(move || set_credentials(&groups, gid, uid).map_err(io::Error::from));
+            }
+            return Ok(());
+        }
+
+        let groups: Option<Vec<Gid>> = self
+            .groups
+            .as_ref()
+            .map(|groups| {
+                groups
+                    .iter()
+                    .map(|group| resolve_gid(group).map(Gid::from_raw))
+                    .collect()
+            })
+            .transpose()?;
+        let gid = self.group.as_ref().map(resolve_gid).transpose()?;
+        if groups.is_none() && gid.is_none() {
+            return Ok(());
+        }
+        // SAFETY: setgroups 和 setgid 都是异步信号安全的，闭包中不分配内存也不获取锁
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(groups) = &groups {
+                    setgroups(groups)?;
+                }
+                if let Some(gid) = gid {
+                    setgid(Gid::from_raw(gid))?;
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+}
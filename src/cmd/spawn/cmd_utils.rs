@@ -4,121 +4,181 @@
 //!
 //! 该模块包含以下主要功能：
 //! - 执行外部命令并获取输出
+//! - 执行交互式的外部命令
+//! - 在伪终端中执行外部命令
+//! - 异步执行外部命令并收集全部输出
 //! - 检查进程是否存活
 //! - 杀死进程
+//! - 优雅地停止进程
 use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::spawn_builder::SpawnBuilder;
+use crate::cmd::spawn::spawn_handle::{CmdExitStatus, SpawnHandle, StopStage};
+use crate::cmd::spawn::spawn_pty::{PtyHandle, PtySize};
+use crate::cmd::std::CmdBuilder;
+use crate::process::{SignalError, send_signal_by_instruction};
 use bytes::Bytes;
-use tracing::{debug, error, trace, warn};
-use std::process::Stdio;
-use tokio::io::{AsyncReadExt, BufReader};
-use tokio::process::{Child, ChildStdout, Command};
+use nix::errno::Errno;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+use tokio::process::Child;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::oneshot;
+use tokio::time::timeout;
 
 /// # 执行外部命令进程
 ///
-/// 执行指定的外部命令进程并返回其子进程句柄。注意：调用此函数后，
-/// `Child` 实例的所有权将转移给调用者，同时 `Child.stdout` 的所有权
-/// 会被移动用于异步读取。
+/// 执行指定的外部命令进程并返回其句柄。子进程由后台任务持有，
+/// 进程被回收且标准输出和标准错误都读取结束后，退出状态会通过 `process_exit_sender` 发送。
+/// 标准错误输出会被丢弃，需要捕获标准错误时，请使用 [SpawnBuilder]。
 ///
 /// ## 参数
 ///
 /// * `cmd` - 要执行的命令名称
 /// * `args` - 命令参数切片
 /// * `data_sender` - 用于发送命令输出数据的广播发送者
-/// * `process_exit_sender` - 用于发送进程结束信号的通道发送者
+/// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
 /// * `read_buffer_size` - 读取缓冲区大小
 ///
 /// ## 返回值
 ///
-/// 返回命令的子进程句柄，或者包含错误信息的 [CmdError]。
+/// 返回子进程句柄 [SpawnHandle]，或者包含错误信息的 [CmdError]。
 ///
 /// ## 示例
 ///
 /// ```rust
 /// use wheel_rs::cmd::spawn::cmd_utils::execute;
-/// use tokio::sync::broadcast;
-/// use std::sync::mpsc;
+/// use tokio::sync::{broadcast, oneshot};
 ///
-/// let (data_sender, _) = broadcast::channel(100);
-/// let (process_exit_sender, _) = mpsc::channel();
-/// let child = execute("ls", &["-l"], data_sender, process_exit_sender, None);
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let (data_sender, _) = broadcast::channel(100);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     let _handle = execute("ls", &["-l"], data_sender, process_exit_sender, 1024).unwrap();
+///     let exit_status = process_exit_receiver.await.unwrap();
+///     assert!(exit_status.success());
+/// }
 /// ```
 pub fn execute(
     cmd: &str,
     args: &[&str],
     data_sender: Sender<Bytes>,
-    process_exit_sender: oneshot::Sender<()>,
+    process_exit_sender: oneshot::Sender<CmdExitStatus>,
     read_buffer_size: usize,
-) -> Result<Child, CmdError> {
-    debug!("command execute start: {} {}", cmd, args.join(" "));
-    let mut child = Command::new(cmd) // 创建新的命令实例
-        .args(args) // 添加命令参数
-        .stdout(Stdio::piped()) // 将标准输出重定向到管道，以便父进程可以读取
-        .stderr(Stdio::null()) // 丢弃标准错误输出
-        .spawn() // 启动命令并返回子进程句柄
-        .map_err(CmdError::Execute)?; // 将可能的错误转换为CmdError类型
-    debug!("command execute started: {}", cmd);
-    // 获取标准输出
-    let stdout = child.stdout.take().ok_or_else(CmdError::TakeStdout)?;
-
-    // 异步读取输出
-    tokio::spawn(read_stdout(
-        stdout,
-        data_sender,
-        process_exit_sender,
-        read_buffer_size,
-    ));
-
-    Ok(child)
+) -> Result<SpawnHandle, CmdError> {
+    SpawnBuilder::new(CmdBuilder::new(cmd).args(args.iter().copied()))
+        .read_buffer_size(read_buffer_size)
+        .spawn(data_sender, process_exit_sender)
 }
 
-/// # 读取子进程的输出
+/// # 执行交互式的外部命令进程
 ///
-/// 异步读取子进程的输出并转发给指定的发送者。
+/// 与 [execute] 类似，但子进程的标准输入连接到管道，通过返回句柄的 [SpawnHandle::stdin]
+/// 写入数据，用于驱动 `sqlite3`、`redis-cli` 等交互式的子进程。
 ///
 /// ## 参数
 ///
-/// * `stdout` - 子进程的输出流
-/// * `data_sender` - 用于转发输出数据的广播发送者
-/// * `process_exit_sender` - 用于发送进程结束信号的通道发送者
+/// * `cmd` - 要执行的命令名称
+/// * `args` - 命令参数切片
+/// * `data_sender` - 用于发送命令输出数据的广播发送者
+/// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
 /// * `read_buffer_size` - 读取缓冲区大小
 ///
 /// ## 返回值
 ///
-/// 无返回值，因为该函数是异步的。
-async fn read_stdout(
-    stdout: ChildStdout,
+/// 返回子进程句柄 [SpawnHandle]，或者包含错误信息的 [CmdError]。
+///
+/// ## 错误处理
+///
+/// 如果命令执行失败，则返回相应的 [CmdError]。
+pub fn execute_interactive(
+    cmd: &str,
+    args: &[&str],
     data_sender: Sender<Bytes>,
-    process_exit_sender: oneshot::Sender<()>,
+    process_exit_sender: oneshot::Sender<CmdExitStatus>,
     read_buffer_size: usize,
-) {
-    let mut reader = BufReader::new(stdout);
-    let mut buffer = vec![0u8; read_buffer_size];
-    loop {
-        match reader.read(&mut buffer).await {
-            Ok(0) => {
-                debug!("command process stdout closed");
-                break;
-            }
-            Ok(n) => {
-                // 有订阅者才发送消息
-                let receiver_count = data_sender.receiver_count();
-                if receiver_count > 0 {
-                    trace!("command process receiver count: {}", receiver_count);
-                    let data = Bytes::copy_from_slice(&buffer[..n]);
-                    if let Err(e) = data_sender.send(data) {
-                        warn!("Failed to send command process output to receiver: {:#}", e);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("read command process stdout error: {:#}", e);
-                break;
-            }
-        }
-    }
-    let _ = process_exit_sender.send(());
+) -> Result<SpawnHandle, CmdError> {
+    SpawnBuilder::new(CmdBuilder::new(cmd).args(args.iter().copied()))
+        .read_buffer_size(read_buffer_size)
+        .stdin_writer()
+        .spawn(data_sender, process_exit_sender)
+}
+
+/// # 在伪终端中执行外部命令进程
+///
+/// 与 [execute] 类似，但子进程的标准输入、标准输出和标准错误都连接到新分配的伪终端，
+/// 终端输出通过 `data_sender` 广播。返回的句柄可用于写入终端输入和调整窗口大小。
+///
+/// ## 参数
+///
+/// * `cmd` - 要执行的命令名称
+/// * `args` - 命令参数切片
+/// * `data_sender` - 用于发送终端输出数据的广播发送者
+/// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
+/// * `size` - 终端窗口大小
+///
+/// ## 返回值
+///
+/// 返回伪终端句柄 [PtyHandle]，或者包含错误信息的 [CmdError]。
+///
+/// ## 错误处理
+///
+/// 如果打开伪终端或启动命令失败，则返回相应的 [CmdError]。
+pub fn execute_pty(
+    cmd: &str,
+    args: &[&str],
+    data_sender: Sender<Bytes>,
+    process_exit_sender: oneshot::Sender<CmdExitStatus>,
+    size: PtySize,
+) -> Result<PtyHandle, CmdError> {
+    SpawnBuilder::new(CmdBuilder::new(cmd).args(args.iter().copied())).spawn_pty(
+        data_sender,
+        process_exit_sender,
+        size,
+    )
+}
+
+/// # 异步执行外部命令并收集输出
+///
+/// [cmd::std::cmd_utils::execute](crate::cmd::std::cmd_utils::execute) 的异步版本，
+/// 等待命令执行完成并返回其标准输出，不会阻塞 tokio 运行时。
+/// 需要设置标准输入、超时时间或输出大小上限时，请使用 [SpawnBuilder::output]。
+///
+/// ## 参数
+///
+/// * `cmd` - 要执行的命令名称
+/// * `args` - 命令参数切片
+///
+/// ## 返回值
+///
+/// 返回命令的标准输出字节向量，或者包含错误信息的 [CmdError]。
+///
+/// ## 错误处理
+///
+/// 与 [cmd::std::cmd_utils::execute](crate::cmd::std::cmd_utils::execute) 相同，
+/// 命令返回非零退出码时返回携带完整输出的 [CmdError::Run] 错误。
+///
+/// ## 示例
+///
+/// ```
+/// use wheel_rs::cmd::cmd_error::CmdError;
+/// use wheel_rs::cmd::spawn::cmd_utils::execute_output;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let output = execute_output("echo", &["Hello, world!"]).await.unwrap();
+///     assert_eq!(output, b"Hello, world!\n");
+///
+///     match execute_output("sh", &["-c", "exit 2"]).await {
+///         Err(CmdError::Run(output)) => assert_eq!(output.code, Some(2)),
+///         _ => unreachable!(),
+///     }
+/// }
+/// ```
+pub async fn execute_output(cmd: &str, args: &[&str]) -> Result<Vec<u8>, CmdError> {
+    Ok(SpawnBuilder::new(CmdBuilder::new(cmd).args(args.iter().copied()))
+        .output()
+        .await?
+        .stdout)
 }
 
 /// # 检查进程是否还活着
@@ -174,3 +234,81 @@ pub async fn kill_process(mut child: Child) -> Result<(), CmdError> {
         CmdError::Kill(e)
     })?)
 }
+
+/// # 优雅地停止进程
+///
+/// 先按指令向子进程发送信号（通常为 `"terminate"`，即 `SIGTERM`），在宽限期内等待其退出；
+/// 超过宽限期仍未退出时，升级为 `SIGKILL` 强制杀死并回收进程。
+/// 与 [terminate_process](crate::process::terminate_process) 的策略相同，但作用于持有所有权的 `Child`。
+///
+/// ## 参数
+///
+/// * `child` - 要停止的子进程
+/// * `instruction` - 首先发送的信号指令，支持的指令见 [send_signal_by_instruction]
+/// * `grace_period` - 发送信号后等待进程退出的宽限期
+///
+/// ## 返回值
+///
+/// 返回结束进程的阶段 [StopStage]，或者包含错误信息的 [CmdError]。
+/// 发送信号时进程已经不存在（`ESRCH`），回收子进程后返回 [StopStage::AlreadyExited]。
+///
+/// ## 错误处理
+///
+/// * 如果无法获取进程ID，则返回 [CmdError::EmptyId] 错误。
+/// * 如果信号指令无效或发送信号失败，则返回 [CmdError::Signal] 错误。
+/// * 如果等待进程退出失败，则返回 [CmdError::Wait] 错误。
+/// * 如果强制杀死进程失败，则返回 [CmdError::Kill] 错误。
+///
+/// ## 示例
+///
+/// ```
+/// use std::time::Duration;
+/// use tokio::process::Command;
+/// use wheel_rs::cmd::spawn::{stop_process, StopStage};
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let child = Command::new("sleep").arg("10").spawn().unwrap();
+///     let stage = stop_process(child, "terminate", Duration::from_secs(5)).await.unwrap();
+///     assert_eq!(stage, StopStage::Graceful);
+/// }
+/// ```
+pub async fn stop_process(
+    mut child: Child,
+    instruction: &str,
+    grace_period: Duration,
+) -> Result<StopStage, CmdError> {
+    if child.try_wait().map_err(CmdError::Wait)?.is_some() {
+        return Ok(StopStage::AlreadyExited);
+    }
+    let pid = child.id().ok_or(CmdError::EmptyId)?;
+    debug!("stopping process: {pid}");
+    match send_signal_by_instruction(instruction, pid) {
+        Ok(()) => {}
+        Err(SignalError::SendSignal(_, _, Errno::ESRCH)) => {
+            debug!("process already exited: {pid}");
+            // 进程可能已经被其它代码回收，此时 waitpid 返回 ECHILD
+            match child.wait().await {
+                Err(e) if e.raw_os_error() != Some(Errno::ECHILD as i32) => {
+                    return Err(CmdError::Wait(e));
+                }
+                _ => return Ok(StopStage::AlreadyExited),
+            }
+        }
+        Err(e) => return Err(e.into()),
+    }
+    match timeout(grace_period, child.wait()).await {
+        Ok(status) => {
+            debug!("process stopped gracefully: {pid} ({})", status.map_err(CmdError::Wait)?);
+            Ok(StopStage::Graceful)
+        }
+        Err(_) => {
+            warn!("process did not exit within {grace_period:?}, killing: {pid}");
+            child.kill().await.map_err(|e| {
+                error!("kill process fail: {:#}", e);
+                CmdError::Kill(e)
+            })?;
+            Ok(StopStage::Killed)
+        }
+    }
+}
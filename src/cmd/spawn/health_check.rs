@@ -0,0 +1,261 @@
+//! # 健康检查模块
+//!
+//! 提供 [HealthCheck] 结构体，用于检查被守护的子进程是否就绪。子进程存活并不代表其能正常提供服务，
+//! 健康检查按固定间隔执行探针，连续失败达到阈值时重启子进程或发布不健康事件。
+
+use crate::addr_utils::Addr;
+use crate::cmd::spawn::spawn_builder::SpawnBuilder;
+use crate::cmd::spawn::supervisor::SupervisorEvent;
+use crate::cmd::std::CmdBuilder;
+use bytes::Bytes;
+use regex::Regex;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::time::{Instant, MissedTickBehavior, interval_at, timeout};
+use tracing::{debug, warn};
+
+/// # 健康检查探针
+#[derive(Debug, Clone)]
+pub enum HealthProbe {
+    /// 执行命令，命令在超时时间内执行成功视为健康
+    Command(Box<CmdBuilder>),
+    /// 连接 TCP 地址，在超时时间内连接成功视为健康，地址必须包含端口
+    Tcp(Addr),
+    /// 检查子进程的输出，两次检查之间输出了匹配正则表达式的行视为健康
+    ///
+    /// 每个输出数据块按换行符拆分后逐行匹配，建议配合
+    /// [OutputFraming::lines](crate::cmd::spawn::OutputFraming::lines) 使用，避免一行输出被拆分到两个数据块中。
+    OutputMatch(Regex),
+}
+
+/// # 不健康时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnhealthyAction {
+    /// 停止子进程，并按重启策略将其视为执行失败处理
+    #[default]
+    Restart,
+    /// 仅发布 [SupervisorEvent::Unhealthy] 事件
+    Notify,
+}
+
+/// # 健康检查
+///
+/// 子进程启动并经过初始延迟后，按检查间隔执行探针。探针第一次成功和从不健康恢复时发布
+/// [SupervisorEvent::Healthy] 事件；连续失败次数达到阈值时发布 [SupervisorEvent::Unhealthy] 事件，
+/// 并按 [UnhealthyAction] 处理。
+///
+/// ## 示例
+///
+/// ```
+/// use regex::Regex;
+/// use std::time::Duration;
+/// use tokio::sync::broadcast;
+/// use wheel_rs::cmd::spawn::{
+///     HealthCheck, HealthProbe, OutputFraming, RestartPolicy, SpawnBuilder, Supervisor,
+///     SupervisorEvent,
+/// };
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     // 输出两次心跳后卡住
+///     let script = "echo heartbeat; sleep 0.1; echo heartbeat; exec sleep 30";
+///     let builder = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", script]))
+///         .framing(OutputFraming::lines());
+///     let check = HealthCheck::new(HealthProbe::OutputMatch(Regex::new("^heartbeat$").unwrap()))
+///         .interval(Duration::from_millis(200))
+///         .failure_threshold(2);
+///     let supervisor = Supervisor::new(builder)
+///         .policy(RestartPolicy::Never)
+///         .health_check(check);
+///     let mut events = supervisor.subscribe();
+///     let (data_sender, _) = broadcast::channel(16);
+///     let _handle = supervisor.start(data_sender);
+///
+///     let mut seen = Vec::new();
+///     loop {
+///         let event = events.recv().await.unwrap();
+///         if event == SupervisorEvent::Stopped {
+///             break;
+///         }
+///         seen.push(event);
+///     }
+///     assert!(seen.contains(&SupervisorEvent::Healthy));
+///     assert!(seen.contains(&SupervisorEvent::Unhealthy { failures: 2 }));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    /// 探针
+    probe: HealthProbe,
+    /// 子进程启动后第一次检查前的延迟，为 `None` 时等于检查间隔
+    initial_delay: Option<Duration>,
+    /// 检查间隔
+    interval: Duration,
+    /// 单次探针的超时时间
+    timeout: Duration,
+    /// 判定为不健康的连续失败次数
+    failure_threshold: u32,
+    /// 不健康时的处理方式
+    action: UnhealthyAction,
+}
+
+impl HealthCheck {
+    /// # 创建健康检查
+    ///
+    /// 默认每 10 秒检查一次，单次探针超时时间为 5 秒，连续失败 3 次判定为不健康并重启子进程。
+    ///
+    /// ## 参数
+    ///
+    /// * `probe` - 健康检查探针
+    pub fn new(probe: HealthProbe) -> Self {
+        Self {
+            probe,
+            initial_delay: None,
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(5),
+            failure_threshold: 3,
+            action: UnhealthyAction::default(),
+        }
+    }
+
+    /// # 设置子进程启动后第一次检查前的延迟
+    ///
+    /// 默认在经过一个检查间隔后进行第一次检查。
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = Some(initial_delay);
+        self
+    }
+
+    /// # 设置检查间隔
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// # 设置单次探针的超时时间
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// # 设置判定为不健康的连续失败次数
+    ///
+    /// 小于 1 时按 1 处理。
+    pub fn failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// # 设置不健康时的处理方式
+    pub fn action(mut self, action: UnhealthyAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// # 是否需要检查子进程的输出
+    pub(crate) fn watches_output(&self) -> bool {
+        matches!(self.probe, HealthProbe::OutputMatch(_))
+    }
+
+    /// # 监控子进程的健康状态
+    ///
+    /// 按检查间隔执行探针并发布健康事件。仅当判定为不健康且处理方式为 [UnhealthyAction::Restart] 时返回，
+    /// 否则一直运行，直到被调用者取消。
+    ///
+    /// ## 参数
+    ///
+    /// * `output` - 子进程输出的订阅者，探针为 [HealthProbe::OutputMatch] 时使用
+    /// * `events` - 守护事件发送者
+    pub(crate) async fn monitor(
+        &self,
+        mut output: Option<broadcast::Receiver<Bytes>>,
+        events: &broadcast::Sender<SupervisorEvent>,
+    ) {
+        let start = Instant::now() + self.initial_delay.unwrap_or(self.interval);
+        let mut ticker = interval_at(start, self.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut output_matched = false;
+        let mut failures = 0;
+        let mut healthy = false;
+        loop {
+            let output_recv = async {
+                match output.as_mut() {
+                    Some(output) => output.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = ticker.tick() => {}
+                data = output_recv => {
+                    match data {
+                        Ok(data) => output_matched |= self.match_output(&data),
+                        Err(broadcast::error::RecvError::Lagged(count)) => {
+                            warn!("health check output lagged, {count} messages skipped");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => output = None,
+                    }
+                    continue;
+                }
+            }
+
+            let passed = self.probe(std::mem::take(&mut output_matched)).await;
+            debug!("health check passed: {passed}");
+            if passed {
+                failures = 0;
+                if !healthy {
+                    healthy = true;
+                    let _ = events.send(SupervisorEvent::Healthy);
+                }
+                continue;
+            }
+            failures += 1;
+            warn!("health check failed {failures} times");
+            if failures == self.failure_threshold {
+                healthy = false;
+                let _ = events.send(SupervisorEvent::Unhealthy { failures });
+                if self.action == UnhealthyAction::Restart {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// # 执行一次探针
+    ///
+    /// `output_matched` 为自上次检查以来是否输出了匹配的行。
+    async fn probe(&self, output_matched: bool) -> bool {
+        match &self.probe {
+            HealthProbe::Command(command) => {
+                let builder = SpawnBuilder::new(command.as_ref().clone().timeout(self.timeout));
+                match builder.output().await {
+                    Ok(output) => output.success(),
+                    Err(e) => {
+                        warn!("health check command error: {e}");
+                        false
+                    }
+                }
+            }
+            HealthProbe::Tcp(addr) => {
+                let Some(port) = addr.port else {
+                    warn!("health check address has no port: {}", addr.host);
+                    return false;
+                };
+                let connect = TcpStream::connect((addr.host.as_str(), port));
+                matches!(timeout(self.timeout, connect).await, Ok(Ok(_)))
+            }
+            HealthProbe::OutputMatch(_) => output_matched,
+        }
+    }
+
+    /// # 检查输出数据块中是否有匹配的行
+    fn match_output(&self, data: &[u8]) -> bool {
+        let HealthProbe::OutputMatch(pattern) = &self.probe else {
+            return false;
+        };
+        String::from_utf8_lossy(data)
+            .lines()
+            .any(|line| pattern.is_match(line))
+    }
+}
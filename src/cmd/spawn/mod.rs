@@ -1,4 +1,28 @@
 pub mod cmd_utils;
+pub mod health_check;
+pub mod output_backlog;
+pub mod output_framing;
+pub mod output_log;
+pub mod spawn_builder;
+pub mod spawn_expect;
+pub mod spawn_handle;
+pub mod spawn_output;
+pub mod spawn_pipeline;
+pub mod spawn_pty;
+pub mod spawn_stdin;
+pub mod supervisor;
 
 // 重新导出结构体，简化外部引用
 pub use cmd_utils::*;
+pub use health_check::*;
+pub use output_backlog::*;
+pub use output_framing::*;
+pub use output_log::*;
+pub use spawn_builder::*;
+pub use spawn_expect::*;
+pub use spawn_handle::*;
+pub use spawn_output::*;
+pub use spawn_pipeline::*;
+pub use spawn_pty::*;
+pub use spawn_stdin::*;
+pub use supervisor::*;
@@ -0,0 +1,322 @@
+//! # 输出积压缓冲模块
+//!
+//! 广播方式只在有订阅者时转发输出，中途订阅运行中子进程的客户端看不到订阅之前的输出。
+//! 本模块提供 [OutputBacklog] 有界环形缓冲区，保留子进程最近的输出，
+//! 新的订阅者先收到积压的输出，再无缝衔接实时输出，适用于“查看任务日志”等场景。
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// 实时输出广播通道的默认容量
+const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// # 积压缓冲区的容量上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacklogLimit {
+    /// 按字节数限制
+    ///
+    /// 超出时从最早的记录开始丢弃，单条记录超过上限时不会被保留。
+    Bytes(usize),
+    /// 按记录数限制
+    ///
+    /// 记录即转发的每个数据块，按行分帧时即为行数。
+    Lines(usize),
+}
+
+/// # 积压缓冲区的状态
+#[derive(Debug)]
+struct BacklogState {
+    /// 容量上限
+    limit: BacklogLimit,
+    /// 保留的记录
+    frames: VecDeque<Bytes>,
+    /// 保留的记录的总字节数
+    bytes: usize,
+    /// 实时输出的广播发送者，创建缓冲区时打开，所有写入者都结束后为 `None`
+    sender: Option<broadcast::Sender<Bytes>>,
+    /// 广播通道的容量
+    capacity: usize,
+    /// 写入者的数量
+    writers: usize,
+}
+
+impl BacklogState {
+    /// # 追加记录
+    fn push(&mut self, data: Bytes) {
+        self.bytes += data.len();
+        self.frames.push_back(data);
+        loop {
+            let over = match self.limit {
+                BacklogLimit::Bytes(max) => self.bytes > max,
+                BacklogLimit::Lines(max) => self.frames.len() > max,
+            };
+            if !over {
+                break;
+            }
+            match self.frames.pop_front() {
+                Some(frame) => self.bytes -= frame.len(),
+                None => break,
+            }
+        }
+    }
+}
+
+/// # 输出积压缓冲区
+///
+/// 作为子进程输出的投递目标，保留最近的输出并广播实时输出。缓冲区可以被克隆，所有克隆共享同一个缓冲区。
+/// 订阅时在同一把锁内复制积压的输出并订阅实时输出，保证订阅者收到的输出既不遗漏也不重复。
+/// 在子进程启动之前订阅，同样可以收到子进程的全部实时输出。
+/// 使用缓冲区的所有子进程的输出都读取结束后，订阅者在收完剩余输出后会收到 [RecvError::Closed]。
+///
+/// ## 示例
+///
+/// ```
+/// use tokio::sync::oneshot;
+/// use wheel_rs::cmd::spawn::{BacklogLimit, OutputBacklog, OutputFraming, SpawnBuilder};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let backlog = OutputBacklog::new(BacklogLimit::Lines(2));
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     SpawnBuilder::new(CmdBuilder::new("seq").arg("5"))
+///         .framing(OutputFraming::lines())
+///         .spawn(backlog.clone(), process_exit_sender)
+///         .unwrap();
+///     process_exit_receiver.await.unwrap();
+///
+///     // 子进程结束后订阅，仍然可以收到最近的 2 行输出
+///     let mut receiver = backlog.subscribe();
+///     assert_eq!(receiver.recv().await.unwrap(), "4");
+///     assert_eq!(receiver.recv().await.unwrap(), "5");
+///     assert!(receiver.recv().await.is_err());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputBacklog {
+    /// 共享状态
+    state: Arc<Mutex<BacklogState>>,
+}
+
+impl OutputBacklog {
+    /// # 创建输出积压缓冲区
+    ///
+    /// ## 参数
+    ///
+    /// * `limit` - 缓冲区的容量上限
+    pub fn new(limit: BacklogLimit) -> Self {
+        Self::with_capacity(limit, DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// # 创建指定实时广播通道容量的输出积压缓冲区
+    ///
+    /// 订阅者处理实时输出的速度落后超过 `capacity` 条记录时会丢失消息，收到 [RecvError::Lagged]。
+    ///
+    /// ## 参数
+    ///
+    /// * `limit` - 缓冲区的容量上限
+    /// * `capacity` - 实时输出广播通道的容量
+    pub fn with_capacity(limit: BacklogLimit, capacity: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BacklogState {
+                limit,
+                frames: VecDeque::new(),
+                bytes: 0,
+                sender: Some(broadcast::channel(capacity).0),
+                capacity,
+                writers: 0,
+            })),
+        }
+    }
+
+    /// # 订阅输出
+    ///
+    /// 返回的接收者先依次产出积压的输出，再产出订阅之后的实时输出。
+    pub fn subscribe(&self) -> BacklogReceiver {
+        let state = self.lock();
+        let receiver = match &state.sender {
+            Some(sender) => sender.subscribe(),
+            // 写入者都已结束，返回一个立即结束的接收者
+            None => broadcast::channel(1).1,
+        };
+        BacklogReceiver {
+            backlog: state.frames.clone(),
+            receiver,
+        }
+    }
+
+    /// # 获取积压的输出
+    pub fn snapshot(&self) -> Vec<Bytes> {
+        self.lock().frames.iter().cloned().collect()
+    }
+
+    /// # 清空积压的输出
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.frames.clear();
+        state.bytes = 0;
+    }
+
+    /// # 创建写入者
+    ///
+    /// 最后一个写入者被丢弃时关闭实时广播通道，之后再创建写入者时（如子进程被重启）重新打开。
+    pub(crate) fn writer(&self) -> BacklogWriter {
+        let mut state = self.lock();
+        state.writers += 1;
+        if state.sender.is_none() {
+            state.sender = Some(broadcast::channel(state.capacity).0);
+        }
+        BacklogWriter(Arc::new(WriterToken {
+            backlog: self.clone(),
+        }))
+    }
+
+    /// # 获取实时输出的广播发送者
+    pub(crate) fn sender(&self) -> Option<broadcast::Sender<Bytes>> {
+        self.lock().sender.clone()
+    }
+
+    /// # 追加记录并广播
+    fn push(&self, data: Bytes) {
+        let mut state = self.lock();
+        state.push(data.clone());
+        if let Some(sender) = &state.sender
+            && sender.receiver_count() > 0
+        {
+            let _ = sender.send(data);
+        }
+    }
+
+    /// # 获取共享状态的锁
+    fn lock(&self) -> MutexGuard<'_, BacklogState> {
+        // 临界区内不会发生 panic，锁中毒时继续使用其中的数据
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// # 写入者令牌
+///
+/// 被丢弃时减少写入者计数，最后一个写入者被丢弃时关闭实时广播通道。
+#[derive(Debug)]
+struct WriterToken {
+    /// 所属的积压缓冲区
+    backlog: OutputBacklog,
+}
+
+impl Drop for WriterToken {
+    fn drop(&mut self) {
+        let mut state = self.backlog.lock();
+        state.writers -= 1;
+        if state.writers == 0 {
+            state.sender = None;
+        }
+    }
+}
+
+/// # 积压缓冲区的写入者
+///
+/// 由输出投递方式持有，所有克隆共享同一个写入者计数。
+#[derive(Debug, Clone)]
+pub struct BacklogWriter(Arc<WriterToken>);
+
+impl BacklogWriter {
+    /// # 获取所属的积压缓冲区
+    pub fn backlog(&self) -> &OutputBacklog {
+        &self.0.backlog
+    }
+
+    /// # 写入记录
+    pub(crate) fn send(&self, data: Bytes) {
+        self.0.backlog.push(data);
+    }
+}
+
+/// # 积压缓冲区的接收者
+///
+/// 由 [OutputBacklog::subscribe] 创建。
+#[derive(Debug)]
+pub struct BacklogReceiver {
+    /// 尚未产出的积压输出
+    backlog: VecDeque<Bytes>,
+    /// 实时输出的接收者
+    receiver: broadcast::Receiver<Bytes>,
+}
+
+impl BacklogReceiver {
+    /// # 接收下一条输出
+    ///
+    /// 积压的输出产出完毕后接收实时输出。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 处理实时输出落后时，返回 [RecvError::Lagged] 错误，之后可以继续接收。
+    /// * 所有写入者都结束且输出已接收完毕时，返回 [RecvError::Closed] 错误。
+    pub async fn recv(&mut self) -> Result<Bytes, RecvError> {
+        match self.backlog.pop_front() {
+            Some(data) => Ok(data),
+            None => self.receiver.recv().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::spawn::{OutputFraming, SpawnBuilder};
+    use crate::cmd::std::CmdBuilder;
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn test_limit_by_bytes() {
+        let backlog = OutputBacklog::new(BacklogLimit::Bytes(5));
+        let writer = backlog.writer();
+        writer.send(Bytes::from("ab"));
+        writer.send(Bytes::from("cd"));
+        writer.send(Bytes::from("ef"));
+        assert_eq!(backlog.snapshot(), ["cd", "ef"]);
+        writer.send(Bytes::from("toolong"));
+        assert!(backlog.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_limit_by_lines() {
+        let backlog = OutputBacklog::new(BacklogLimit::Lines(2));
+        let writer = backlog.writer();
+        for line in ["1", "2", "3"] {
+            writer.send(Bytes::from(line));
+        }
+        assert_eq!(backlog.snapshot(), ["2", "3"]);
+    }
+
+    /// 测试在子进程启动之前订阅，可以收到全部实时输出
+    #[tokio::test]
+    async fn test_subscribe_before_spawn() {
+        let backlog = OutputBacklog::new(BacklogLimit::Lines(10));
+        let mut receiver = backlog.subscribe();
+        let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+        SpawnBuilder::new(CmdBuilder::new("seq").arg("2"))
+            .framing(OutputFraming::lines())
+            .spawn(backlog.clone(), process_exit_sender)
+            .unwrap();
+        assert_eq!(receiver.recv().await.unwrap(), "1");
+        assert_eq!(receiver.recv().await.unwrap(), "2");
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+        assert!(process_exit_receiver.await.unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_backlog_then_live() {
+        let backlog = OutputBacklog::new(BacklogLimit::Lines(10));
+        let writer = backlog.writer();
+        writer.send(Bytes::from("old"));
+        let mut receiver = backlog.subscribe();
+        writer.send(Bytes::from("new"));
+        drop(writer);
+        assert_eq!(receiver.recv().await.unwrap(), "old");
+        assert_eq!(receiver.recv().await.unwrap(), "new");
+        assert_eq!(receiver.recv().await, Err(RecvError::Closed));
+    }
+}
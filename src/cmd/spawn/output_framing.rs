@@ -0,0 +1,388 @@
+//! # 输出分帧模块
+//!
+//! 将子进程输出的原始字节流切分为完整的记录（帧），使订阅者每次收到的都是一条完整的记录，
+//! 而不是被任意截断的数据片段。
+//!
+//! 支持以下分帧方式：
+//! - 原始数据块（不分帧）
+//! - 按换行符分帧
+//! - 按自定义分隔符分帧
+//! - 按长度前缀分帧（4 字节大端无符号整数表示记录长度）
+
+use bytes::{Buf, Bytes, BytesMut};
+use tracing::warn;
+
+/// 默认的最大帧长度
+const DEFAULT_MAX_FRAME_LENGTH: usize = 64 * 1024;
+
+/// 长度前缀的字节数
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// # 分帧方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FramingMode {
+    /// 不分帧，按读取到的原始数据块转发
+    #[default]
+    Raw,
+    /// 按换行符 `\n` 分帧，转发的记录不包含换行符及其前面的 `\r`
+    Lines,
+    /// 按自定义分隔符分帧，转发的记录不包含分隔符
+    Delimiter(u8),
+    /// 按长度前缀分帧，每条记录前有 4 字节大端无符号整数表示的记录长度，转发的记录不包含长度前缀
+    LengthPrefixed,
+}
+
+/// # 超长记录处理策略
+///
+/// 当一条记录的长度超过最大帧长度时的处理方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// 只转发记录的前部分（最大帧长度），丢弃剩余部分
+    #[default]
+    Truncate,
+    /// 将记录拆分为多个不超过最大帧长度的帧依次转发
+    Split,
+    /// 丢弃整条记录
+    Discard,
+}
+
+/// # 输出分帧配置
+///
+/// ## 示例
+///
+/// ```
+/// use wheel_rs::cmd::spawn::{OutputFraming, OverflowPolicy};
+///
+/// let framing = OutputFraming::lines()
+///     .max_frame_length(1024)
+///     .overflow(OverflowPolicy::Split);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputFraming {
+    /// 分帧方式
+    pub mode: FramingMode,
+    /// 最大帧长度（字节）
+    pub max_frame_length: usize,
+    /// 超长记录处理策略
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for OutputFraming {
+    fn default() -> Self {
+        Self::new(FramingMode::default())
+    }
+}
+
+impl OutputFraming {
+    /// # 创建指定分帧方式的配置
+    pub fn new(mode: FramingMode) -> Self {
+        Self {
+            mode,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+
+    /// # 不分帧
+    pub fn raw() -> Self {
+        Self::new(FramingMode::Raw)
+    }
+
+    /// # 按换行符分帧
+    pub fn lines() -> Self {
+        Self::new(FramingMode::Lines)
+    }
+
+    /// # 按自定义分隔符分帧
+    pub fn delimiter(delimiter: u8) -> Self {
+        Self::new(FramingMode::Delimiter(delimiter))
+    }
+
+    /// # 按长度前缀分帧
+    pub fn length_prefixed() -> Self {
+        Self::new(FramingMode::LengthPrefixed)
+    }
+
+    /// # 设置最大帧长度
+    pub fn max_frame_length(mut self, max_frame_length: usize) -> Self {
+        self.max_frame_length = max_frame_length.max(1);
+        self
+    }
+
+    /// # 设置超长记录处理策略
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+}
+
+/// # 帧解码器
+///
+/// 按照分帧配置将连续到达的数据块解码为完整的帧。
+#[derive(Debug)]
+pub(crate) struct FrameDecoder {
+    /// 分帧配置
+    framing: OutputFraming,
+    /// 尚未组成完整帧的数据
+    buffer: BytesMut,
+    /// 当前记录是否已超长，超长后直到记录结束前的数据都按超长策略处理
+    overflowed: bool,
+    /// 长度前缀分帧时，当前超长记录尚未处理的字节数
+    pending: usize,
+}
+
+impl FrameDecoder {
+    /// # 创建帧解码器
+    pub(crate) fn new(framing: OutputFraming) -> Self {
+        Self {
+            framing,
+            buffer: BytesMut::new(),
+            overflowed: false,
+            pending: 0,
+        }
+    }
+
+    /// # 解码数据块
+    ///
+    /// 将新到达的数据追加到缓冲区，并返回所有已完整的帧。
+    pub(crate) fn decode(&mut self, data: &[u8]) -> Vec<Bytes> {
+        let mut frames = Vec::new();
+        match self.framing.mode {
+            FramingMode::Raw => frames.push(Bytes::copy_from_slice(data)),
+            FramingMode::Lines => {
+                self.buffer.extend_from_slice(data);
+                self.decode_delimited(b'\n', true, &mut frames);
+            }
+            FramingMode::Delimiter(delimiter) => {
+                self.buffer.extend_from_slice(data);
+                self.decode_delimited(delimiter, false, &mut frames);
+            }
+            FramingMode::LengthPrefixed => {
+                self.buffer.extend_from_slice(data);
+                self.decode_length_prefixed(&mut frames);
+            }
+        }
+        frames
+    }
+
+    /// # 结束解码
+    ///
+    /// 输出流关闭时调用，返回缓冲区中剩余的最后一条不完整的记录。
+    /// 长度前缀分帧时不完整的记录会被丢弃。
+    pub(crate) fn finish(&mut self) -> Option<Bytes> {
+        let remaining = self.buffer.split().freeze();
+        let overflowed = std::mem::take(&mut self.overflowed);
+        match self.framing.mode {
+            FramingMode::Raw => None,
+            FramingMode::Lines | FramingMode::Delimiter(_) => {
+                let drop_remaining = overflowed && self.framing.overflow != OverflowPolicy::Split;
+                (!remaining.is_empty() && !drop_remaining).then_some(remaining)
+            }
+            FramingMode::LengthPrefixed => {
+                if !remaining.is_empty() || self.pending > 0 {
+                    warn!("discard incomplete length-prefixed frame at end of output");
+                }
+                self.pending = 0;
+                None
+            }
+        }
+    }
+
+    /// # 按分隔符解码
+    fn decode_delimited(&mut self, delimiter: u8, strip_cr: bool, frames: &mut Vec<Bytes>) {
+        let max = self.framing.max_frame_length;
+        loop {
+            match self.buffer.iter().position(|b| *b == delimiter) {
+                Some(index) => {
+                    let mut record = self.buffer.split_to(index).freeze();
+                    self.buffer.advance(1);
+                    if strip_cr && record.last() == Some(&b'\r') {
+                        record.truncate(record.len() - 1);
+                    }
+                    // 超长记录的剩余部分，仅拆分策略需要转发非空的剩余部分
+                    if std::mem::take(&mut self.overflowed)
+                        && (self.framing.overflow != OverflowPolicy::Split || record.is_empty())
+                    {
+                        continue;
+                    }
+                    if record.len() > max {
+                        self.push_overflowed(record, frames);
+                    } else {
+                        frames.push(record);
+                    }
+                }
+                None => {
+                    if !self.overflowed && self.buffer.len() > max {
+                        warn!("command process output record exceeds max frame length: {max}");
+                        self.overflowed = true;
+                        if self.framing.overflow == OverflowPolicy::Truncate {
+                            frames.push(self.buffer.split_to(max).freeze());
+                        }
+                    }
+                    if self.overflowed {
+                        if self.framing.overflow == OverflowPolicy::Split {
+                            // 保留最后一段不足最大帧长度的数据，等待记录结束
+                            while self.buffer.len() >= max {
+                                frames.push(self.buffer.split_to(max).freeze());
+                            }
+                        } else {
+                            self.buffer.clear();
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    /// # 按超长策略处理超长记录
+    fn push_overflowed(&self, record: Bytes, frames: &mut Vec<Bytes>) {
+        let max = self.framing.max_frame_length;
+        warn!(
+            "command process output record exceeds max frame length: {} > {}",
+            record.len(),
+            max
+        );
+        match self.framing.overflow {
+            OverflowPolicy::Truncate => frames.push(record.slice(..max)),
+            OverflowPolicy::Split => {
+                frames.extend(record.chunks(max).map(|chunk| record.slice_ref(chunk)))
+            }
+            OverflowPolicy::Discard => {}
+        }
+    }
+
+    /// # 按长度前缀解码
+    fn decode_length_prefixed(&mut self, frames: &mut Vec<Bytes>) {
+        let max = self.framing.max_frame_length;
+        loop {
+            // 处理超长记录尚未处理的部分
+            if self.pending > 0 {
+                if self.framing.overflow == OverflowPolicy::Split {
+                    let len = self.pending.min(max);
+                    if self.buffer.len() < len {
+                        break;
+                    }
+                    frames.push(self.buffer.split_to(len).freeze());
+                    self.pending -= len;
+                } else {
+                    let len = self.pending.min(self.buffer.len());
+                    self.buffer.advance(len);
+                    self.pending -= len;
+                    if self.pending > 0 {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            if self.buffer.len() < LENGTH_PREFIX_SIZE {
+                break;
+            }
+            let len =
+                u32::from_be_bytes(self.buffer[..LENGTH_PREFIX_SIZE].try_into().unwrap()) as usize;
+            if len <= max {
+                if self.buffer.len() < LENGTH_PREFIX_SIZE + len {
+                    break;
+                }
+                self.buffer.advance(LENGTH_PREFIX_SIZE);
+                frames.push(self.buffer.split_to(len).freeze());
+                continue;
+            }
+
+            warn!(
+                "command process output record exceeds max frame length: {} > {}",
+                len, max
+            );
+            match self.framing.overflow {
+                OverflowPolicy::Truncate => {
+                    if self.buffer.len() < LENGTH_PREFIX_SIZE + max {
+                        break;
+                    }
+                    self.buffer.advance(LENGTH_PREFIX_SIZE);
+                    frames.push(self.buffer.split_to(max).freeze());
+                    self.pending = len - max;
+                }
+                OverflowPolicy::Split | OverflowPolicy::Discard => {
+                    self.buffer.advance(LENGTH_PREFIX_SIZE);
+                    self.pending = len;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(framing: OutputFraming, chunks: &[&[u8]]) -> Vec<Bytes> {
+        let mut decoder = FrameDecoder::new(framing);
+        let mut frames: Vec<Bytes> = chunks
+            .iter()
+            .flat_map(|chunk| decoder.decode(chunk))
+            .collect();
+        frames.extend(decoder.finish());
+        frames
+    }
+
+    #[test]
+    fn test_lines_across_chunks() {
+        let frames = decode_all(
+            OutputFraming::lines(),
+            &[b"hel", b"lo\r\nwor", b"ld\n", b"tail"],
+        );
+        assert_eq!(frames, vec!["hello", "world", "tail"]);
+    }
+
+    #[test]
+    fn test_custom_delimiter() {
+        let frames = decode_all(OutputFraming::delimiter(0), &[b"a\0b", b"c\0"]);
+        assert_eq!(frames, vec!["a", "bc"]);
+    }
+
+    #[test]
+    fn test_lines_overflow_truncate() {
+        let framing = OutputFraming::lines().max_frame_length(3);
+        let frames = decode_all(framing, &[b"abcd", b"ef\nxy\n"]);
+        assert_eq!(frames, vec!["abc", "xy"]);
+    }
+
+    #[test]
+    fn test_lines_overflow_split() {
+        let framing = OutputFraming::lines()
+            .max_frame_length(3)
+            .overflow(OverflowPolicy::Split);
+        let frames = decode_all(framing, &[b"abcd", b"efg\nabcdefg\n"]);
+        assert_eq!(frames, vec!["abc", "def", "g", "abc", "def", "g"]);
+    }
+
+    #[test]
+    fn test_lines_overflow_discard() {
+        let framing = OutputFraming::lines()
+            .max_frame_length(3)
+            .overflow(OverflowPolicy::Discard);
+        let frames = decode_all(framing, &[b"abcd", b"ef\nxy\nlonger"]);
+        assert_eq!(frames, vec!["xy"]);
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let frames = decode_all(
+            OutputFraming::length_prefixed(),
+            &[b"\0\0\0\x03ab", b"c\0\0", b"\0\x00\0\0\0\x01z"],
+        );
+        assert_eq!(frames, vec!["abc", "", "z"]);
+    }
+
+    #[test]
+    fn test_length_prefixed_overflow() {
+        let data: &[u8] = b"\0\0\0\x05abcde\0\0\0\x01z";
+        let truncate = OutputFraming::length_prefixed().max_frame_length(2);
+        assert_eq!(decode_all(truncate, &[data]), vec!["ab", "z"]);
+        let split = truncate.overflow(OverflowPolicy::Split);
+        assert_eq!(decode_all(split, &[data]), vec!["ab", "cd", "e", "z"]);
+        let discard = truncate.overflow(OverflowPolicy::Discard);
+        assert_eq!(decode_all(discard, &[data]), vec!["z"]);
+    }
+}
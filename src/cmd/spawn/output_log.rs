@@ -0,0 +1,181 @@
+//! # 输出日志模块
+//!
+//! 提供 [OutputLog] 配置，将子进程的输出在投递给消费者的同时写入按时间轮转的日志文件，
+//! 每个被管理的子进程可以拥有自己的日志文件。轮转由 `tracing_appender::rolling` 实现。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::output_framing::{FramingMode, OutputFraming};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+
+/// 默认的日志文件名后缀
+const DEFAULT_SUFFIX: &str = "log";
+
+/// # 输出日志配置
+///
+/// 日志文件名由前缀、轮转时间和后缀组成，如 `worker.2024-01-01.log`。
+/// 字段都是公开的，支持序列化和反序列化，可以直接嵌入服务的配置结构体中。
+/// 目录和轮转策略分别使用 [path_buf_serde](crate::serde::path_buf_serde)
+/// 和 [rotation_serde](crate::serde::rotation_serde) 进行序列化。
+///
+/// 输出按分帧配置切分后写入日志，分帧时去掉的换行符或分隔符会被补回，
+/// 使日志文件中每条记录各占一行。
+///
+/// ## 示例
+///
+/// ```
+/// use tokio::sync::{broadcast, oneshot};
+/// use tracing_appender::rolling::Rotation;
+/// use wheel_rs::cmd::spawn::{OutputFraming, OutputLog, SpawnBuilder};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let directory = std::env::temp_dir().join(format!("wheel-rs-log-{}", std::process::id()));
+///     let (data_sender, _) = broadcast::channel(16);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     SpawnBuilder::new(CmdBuilder::new("seq").arg("3"))
+///         .framing(OutputFraming::lines())
+///         .output_log(OutputLog::new(&directory, "seq").rotation(Rotation::NEVER))
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///     process_exit_receiver.await.unwrap();
+///
+///     let log = std::fs::read_to_string(directory.join("seq.log")).unwrap();
+///     assert_eq!(log, "1\n2\n3\n");
+///     std::fs::remove_dir_all(directory).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputLog {
+    /// 日志文件所在的目录，不存在时会自动创建
+    #[serde(with = "crate::serde::path_buf_serde")]
+    pub directory: PathBuf,
+    /// 日志文件名前缀
+    pub prefix: String,
+    /// 日志文件名后缀，为 `None` 时不添加后缀
+    pub suffix: Option<String>,
+    /// 轮转策略
+    #[serde(with = "crate::serde::rotation_serde")]
+    pub rotation: Rotation,
+    /// 最多保留的日志文件数量，为 `None` 时不删除旧的日志文件
+    pub max_files: Option<usize>,
+}
+
+impl OutputLog {
+    /// # 创建输出日志配置
+    ///
+    /// 默认每天轮转，后缀为 `log`，不删除旧的日志文件。
+    ///
+    /// ## 参数
+    ///
+    /// * `directory` - 日志文件所在的目录
+    /// * `prefix` - 日志文件名前缀，通常为子进程的名称
+    pub fn new(directory: impl Into<PathBuf>, prefix: impl Into<String>) -> Self {
+        Self {
+            directory: directory.into(),
+            prefix: prefix.into(),
+            suffix: Some(DEFAULT_SUFFIX.to_string()),
+            rotation: Rotation::DAILY,
+            max_files: None,
+        }
+    }
+
+    /// # 设置日志文件名后缀
+    pub fn suffix(mut self, suffix: Option<String>) -> Self {
+        self.suffix = suffix;
+        self
+    }
+
+    /// # 设置轮转策略
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// # 设置最多保留的日志文件数量
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = Some(max_files);
+        self
+    }
+
+    /// # 打开日志文件
+    ///
+    /// ## 参数
+    ///
+    /// * `framing` - 子进程输出的分帧配置，用于补回分帧时去掉的分隔符
+    ///
+    /// ## 错误处理
+    ///
+    /// 创建目录或打开日志文件失败时，返回 [CmdError::OutputLog] 错误。
+    pub(crate) fn open(&self, framing: OutputFraming) -> Result<OutputLogWriter, CmdError> {
+        let mut builder = RollingFileAppender::builder()
+            .rotation(self.rotation.clone())
+            .filename_prefix(self.prefix.as_str());
+        if let Some(suffix) = &self.suffix {
+            builder = builder.filename_suffix(suffix.as_str());
+        }
+        if let Some(max_files) = self.max_files {
+            builder = builder.max_log_files(max_files);
+        }
+        let appender = builder
+            .build(&self.directory)
+            .map_err(CmdError::OutputLog)?;
+        let delimiter = match framing.mode {
+            FramingMode::Raw => None,
+            FramingMode::Lines | FramingMode::LengthPrefixed => Some(b'\n'),
+            FramingMode::Delimiter(delimiter) => Some(delimiter),
+        };
+        Ok(OutputLogWriter {
+            appender: Arc::new(Mutex::new(appender)),
+            delimiter,
+        })
+    }
+}
+
+/// # 输出日志写入器
+///
+/// 可以被克隆，标准输出和标准错误的读取循环共享同一个日志文件。
+/// 文件写入在 `tokio` 的阻塞线程池中执行，磁盘缓慢时不会阻塞异步工作线程。
+#[derive(Debug, Clone)]
+pub(crate) struct OutputLogWriter {
+    /// 按时间轮转的日志文件
+    appender: Arc<Mutex<RollingFileAppender>>,
+    /// 每条记录后补回的分隔符
+    delimiter: Option<u8>,
+}
+
+impl OutputLogWriter {
+    /// # 写入一批记录
+    ///
+    /// 一次读取解码出的所有记录在同一个阻塞任务中写入，等待写入完成后返回，
+    /// 保证同一个管道的记录按顺序写入。写入失败只记录警告，不影响输出的投递。
+    pub(crate) async fn write(&self, frames: Vec<Bytes>) {
+        if frames.is_empty() {
+            return;
+        }
+        let appender = self.appender.clone();
+        let delimiter = self.delimiter;
+        let result = tokio::task::spawn_blocking(move || {
+            let mut appender = appender.lock().unwrap_or_else(|e| e.into_inner());
+            for frame in &frames {
+                appender.write_all(frame)?;
+                if let Some(delimiter) = delimiter {
+                    appender.write_all(&[delimiter])?;
+                }
+            }
+            Ok::<_, io::Error>(())
+        })
+        .await;
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to write command process output log: {:#}", e),
+            Err(e) => warn!("Failed to write command process output log: {:#}", e),
+        }
+    }
+}
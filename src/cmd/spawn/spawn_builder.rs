@@ -0,0 +1,740 @@
+//! # 异步命令构建器模块
+//!
+//! 提供 [SpawnBuilder] 结构体，用于在 tokio 运行时中启动外部命令，
+//! 并将其输出异步转发给订阅者，或者异步等待命令执行完成并收集全部输出。
+//! 命令可以通过管道或伪终端（PTY）与父进程连接。
+//!
+//! 命令本身（参数、环境变量、工作目录等）通过 [CmdBuilder] 描述，
+//! 本构建器在其基础上配置输出相关的选项。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::output_framing::OutputFraming;
+use crate::cmd::spawn::output_log::{OutputLog, OutputLogWriter};
+use crate::cmd::spawn::spawn_handle::{CmdExitStatus, SignalRequest, SpawnHandle};
+use crate::cmd::spawn::spawn_stdin::StdinWriter;
+use crate::cmd::spawn::spawn_output::{
+    OutputDelivery, OutputSink, OutputStream, StderrMode, read_output,
+};
+use crate::cmd::cmd_output::CmdOutput;
+use crate::cmd::process_group::ProcessGroup;
+use crate::cmd::spawn::spawn_pty::{
+    PtyHandle, PtyReader, PtySize, open_pty, set_controlling_terminal,
+};
+use crate::cmd::std::{CmdBuilder, kill_process_by_id, kill_process_group};
+use nix::sys::signal::Signal;
+use std::io;
+use std::io::ErrorKind;
+use std::os::unix::process::CommandExt;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+use tracing::{debug, error, warn};
+
+/// 默认的读取缓冲区大小
+const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
+
+/// # 异步命令构建器
+///
+/// 配置并启动外部命令进程，子进程的输出由后台任务读取并投递给消费者。
+///
+/// ## 示例
+///
+/// ```
+/// use tokio::sync::{broadcast, oneshot};
+/// use wheel_rs::cmd::spawn::{SpawnBuilder, StderrMode};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let (data_sender, mut data_receiver) = broadcast::channel(100);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     let handle = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", "echo oops >&2; exit 3"]))
+///         .stderr(StderrMode::Merge)
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///     assert_eq!(data_receiver.recv().await.unwrap(), "oops\n");
+///     let exit_status = process_exit_receiver.await.unwrap();
+///     assert_eq!(exit_status.code, Some(3));
+///     assert!(!handle.is_alive());
+/// }
+/// ```
+///
+/// 需要保证消费者收到每一个字节时，使用有界通道投递输出：
+///
+/// ```
+/// use tokio::sync::{mpsc, oneshot};
+/// use wheel_rs::cmd::spawn::SpawnBuilder;
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let (data_sender, mut data_receiver) = mpsc::channel(1);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     SpawnBuilder::new(CmdBuilder::new("seq").args(["1", "100000"]))
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///     let mut total = 0;
+///     while let Some(data) = data_receiver.recv().await {
+///         total += data.len();
+///     }
+///     assert_eq!(total, 588895);
+///     assert!(process_exit_receiver.await.unwrap().success());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpawnBuilder {
+    /// 要执行的命令
+    command: CmdBuilder,
+    /// 读取缓冲区大小
+    read_buffer_size: usize,
+    /// 标准错误处理方式
+    stderr: StderrMode,
+    /// 输出分帧配置
+    framing: OutputFraming,
+    /// 收集输出时标准输出和标准错误各自的大小上限
+    max_output_size: Option<usize>,
+    /// 是否将标准输入连接到写入器
+    stdin_writer: bool,
+    /// 输出日志配置
+    output_log: Option<OutputLog>,
+}
+
+impl SpawnBuilder {
+    /// # 创建异步命令构建器
+    ///
+    /// ## 参数
+    ///
+    /// * `command` - 要执行的命令，其标准输入和超时设置仅在 [SpawnBuilder::output] 中使用
+    pub fn new(command: CmdBuilder) -> Self {
+        Self {
+            command,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            stderr: StderrMode::default(),
+            framing: OutputFraming::default(),
+            max_output_size: None,
+            stdin_writer: false,
+            output_log: None,
+        }
+    }
+
+    /// # 替换要执行的命令
+    ///
+    /// 用于管道中的各个阶段共用同一份输出配置。
+    pub(crate) fn command(mut self, command: CmdBuilder) -> Self {
+        self.command = command;
+        self
+    }
+
+    /// # 设置读取缓冲区大小
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// # 设置标准错误处理方式
+    ///
+    /// 默认丢弃标准错误输出。
+    pub fn stderr(mut self, stderr: StderrMode) -> Self {
+        self.stderr = stderr;
+        self
+    }
+
+    /// # 设置输出分帧配置
+    ///
+    /// 默认不分帧，按读取到的原始数据块转发。设置分帧后，订阅者每次收到的都是一条完整的记录，
+    /// 标准输出和标准错误分别独立分帧，合并时也不会出现记录交错。
+    pub fn framing(mut self, framing: OutputFraming) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// # 启用标准输入写入器
+    ///
+    /// 子进程的标准输入将连接到管道，通过 [SpawnHandle::stdin] 获取 [StdinWriter] 写入数据，
+    /// 用于驱动交互式的子进程。默认子进程继承父进程的标准输入。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use tokio::sync::{mpsc, oneshot};
+    /// use wheel_rs::cmd::spawn::{OutputFraming, SpawnBuilder};
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let (data_sender, mut data_receiver) = mpsc::channel(16);
+    ///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+    ///     let repl = "while read expr; do echo $(($expr)); done";
+    ///     let handle = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", repl]))
+    ///         .stdin_writer()
+    ///         .framing(OutputFraming::lines())
+    ///         .spawn(data_sender, process_exit_sender)
+    ///         .unwrap();
+    ///     let stdin = handle.stdin().unwrap();
+    ///     stdin.write("1 + 2\n").await.unwrap();
+    ///     assert_eq!(data_receiver.recv().await.unwrap(), "3");
+    ///     stdin.write("6 * 7\n").await.unwrap();
+    ///     assert_eq!(data_receiver.recv().await.unwrap(), "42");
+    ///     // 关闭标准输入，子进程读到 EOF 后退出
+    ///     stdin.close().await;
+    ///     assert!(process_exit_receiver.await.unwrap().success());
+    /// }
+    /// ```
+    pub fn stdin_writer(mut self) -> Self {
+        self.stdin_writer = true;
+        self
+    }
+
+    /// # 设置输出日志
+    ///
+    /// 子进程的输出在投递给消费者的同时写入按时间轮转的日志文件，标准错误未被丢弃时也会写入同一个日志文件。
+    /// 仅在启动命令时使用，[SpawnBuilder::output] 不会写入日志。详见 [OutputLog]。
+    pub fn output_log(mut self, output_log: OutputLog) -> Self {
+        self.output_log = Some(output_log);
+        self
+    }
+
+    /// # 打开输出日志
+    ///
+    /// 管道的所有阶段共享同一个日志写入器，避免多个写入器同时写入和轮转同一个日志文件。
+    pub(crate) fn open_output_log(&self) -> Result<Option<OutputLogWriter>, CmdError> {
+        self.output_log
+            .as_ref()
+            .map(|output_log| output_log.open(self.framing))
+            .transpose()
+    }
+
+    /// # 获取子进程的标准输入设置
+    pub(crate) fn stdin_stdio(&self) -> Stdio {
+        if self.stdin_writer {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        }
+    }
+
+    /// # 设置收集输出的大小上限
+    ///
+    /// 仅在 [SpawnBuilder::output] 中使用。标准输出或标准错误任意一个超过该大小时，
+    /// 子进程会被强制杀死，并返回 [CmdError::OutputTooLarge] 错误。默认不限制。
+    pub fn max_output_size(mut self, max_output_size: usize) -> Self {
+        self.max_output_size = Some(max_output_size);
+        self
+    }
+
+    /// # 执行命令并收集输出
+    ///
+    /// [CmdBuilder::execute] 的异步版本，不会阻塞 tokio 运行时。启动子进程，写入标准输入数据，
+    /// 并等待其执行完成，标准输出和标准错误会被完整收集。命令的标准输入和超时设置取自 [CmdBuilder]，
+    /// 标准错误处理方式和输出分帧配置不会被使用。
+    ///
+    /// ## 返回值
+    ///
+    /// 返回命令的执行结果 [CmdOutput]，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 资源限制、进程优先级或文件权限掩码无效时，返回相应的 [CmdError]。
+    /// * 无法启动命令或读写管道失败时，返回 [CmdError::Execute] 错误。
+    /// * 命令返回非零退出码或被信号终止时，返回携带完整输出的 [CmdError::Run] 错误。
+    /// * 命令运行超时时，返回 [CmdError::Timeout] 错误。
+    /// * 输出超过大小上限时，返回 [CmdError::OutputTooLarge] 错误。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use wheel_rs::cmd::cmd_error::CmdError;
+    /// use wheel_rs::cmd::spawn::SpawnBuilder;
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let output = SpawnBuilder::new(CmdBuilder::new("cat").stdin("Hello, world!"))
+    ///         .output()
+    ///         .await
+    ///         .unwrap();
+    ///     assert_eq!(output.stdout, b"Hello, world!");
+    ///
+    ///     let result = SpawnBuilder::new(CmdBuilder::new("yes"))
+    ///         .max_output_size(1024)
+    ///         .output()
+    ///         .await;
+    ///     assert!(matches!(result, Err(CmdError::OutputTooLarge(1024))));
+    ///
+    ///     let result = SpawnBuilder::new(
+    ///         CmdBuilder::new("sleep")
+    ///             .arg("10")
+    ///             .timeout(Duration::from_millis(100)),
+    ///     )
+    ///     .output()
+    ///     .await;
+    ///     assert!(matches!(result, Err(CmdError::Timeout(_))));
+    /// }
+    /// ```
+    pub async fn output(&self) -> Result<CmdOutput, CmdError> {
+        debug!("executing command: {}", self.command.command_line());
+        let start = Instant::now();
+        let stdin_data = self.command.get_stdin();
+        let mut child = Command::from(self.command.build_command()?)
+            .stdin(if stdin_data.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(CmdError::Execute)?;
+
+        // 在独立任务中写入标准输入，避免管道缓冲区写满导致死锁
+        let stdin_task = match (child.stdin.take(), stdin_data) {
+            (Some(mut stdin), Some(data)) => {
+                let data = data.to_vec();
+                Some(tokio::spawn(async move {
+                    match stdin.write_all(&data).await {
+                        // 子进程未读取全部输入就关闭了标准输入，不视为错误
+                        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+                        result => result,
+                    }
+                }))
+            }
+            _ => None,
+        };
+        let stdout = child.stdout.take().ok_or(CmdError::TakeStdout())?;
+        let stderr = child.stderr.take().ok_or(CmdError::TakeStderr())?;
+
+        let collected = collect_output(&mut child, stdout, stderr, self.max_output_size);
+        let result = match self.command.get_timeout() {
+            Some(duration) => timeout(duration, collected)
+                .await
+                .unwrap_or(Err(CmdError::Timeout(duration))),
+            None => collected.await,
+        };
+        let (status, stdout, stderr) = match result {
+            Ok(collected) => collected,
+            Err(e) => {
+                warn!("command failed, killing process {:?}: {}", child.id(), e);
+                self.kill_child(&mut child).await?;
+                return Err(e);
+            }
+        };
+
+        if let Some(stdin_task) = stdin_task {
+            stdin_task
+                .await
+                .map_err(|e| CmdError::Execute(io::Error::other(e)))?
+                .map_err(CmdError::Execute)?;
+        }
+        let output = CmdOutput::new(status, stdout, stderr, start.elapsed());
+        debug!("command executed: {} ({})", self.command.command_line(), status);
+
+        if !output.success() {
+            return Err(CmdError::Run(output));
+        }
+
+        Ok(output)
+    }
+
+    /// # 杀死子进程并等待其退出
+    ///
+    /// 子进程为进程组组长时，杀死整个进程组。
+    async fn kill_child(&self, child: &mut Child) -> Result<(), CmdError> {
+        match child.id() {
+            Some(pid) if self.command.get_process_group().is_leader() => {
+                match kill_process_group(pid, Signal::SIGKILL) {
+                    Ok(()) | Err(CmdError::NoSuchProcess(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            _ => child.start_kill().map_err(CmdError::Kill)?,
+        }
+        child.wait().await.map_err(CmdError::Kill)?;
+        Ok(())
+    }
+
+    /// # 启动命令进程
+    ///
+    /// 启动外部命令进程并返回其句柄。子进程由后台任务持有，后台任务负责读取输出、
+    /// 在进程退出后立即回收进程，并在标准输出和标准错误都读取结束后通过 `process_exit_sender`
+    /// 发送最终的退出状态。
+    ///
+    /// ## 参数
+    ///
+    /// * `output` - 命令输出的投递方式，可以直接传入广播发送者或有界通道发送者，
+    ///   详见 [OutputDelivery]
+    /// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
+    ///
+    /// ## 返回值
+    ///
+    /// 返回子进程句柄，或者包含错误信息的 [CmdError]。
+    pub fn spawn(
+        &self,
+        output: impl Into<OutputDelivery>,
+        process_exit_sender: oneshot::Sender<CmdExitStatus>,
+    ) -> Result<SpawnHandle, CmdError> {
+        let sink = OutputSink::from(output.into());
+        let log = self.open_output_log()?;
+        let (handle, _) = self.spawn_stage(
+            self.stdin_stdio(),
+            &sink,
+            true,
+            log,
+            Some(process_exit_sender),
+        )?;
+        Ok(handle)
+    }
+
+    /// # 在伪终端中启动命令进程
+    ///
+    /// 分配一个伪终端，子进程的标准输入、标准输出和标准错误都连接到伪终端的从设备，
+    /// 并成为新会话的首进程，以伪终端作为控制终端。终端输出（包含标准错误）由后台任务读取，
+    /// 按分帧配置投递给消费者；标准错误处理方式不会被使用。
+    /// 子进程是新进程组的组长，通过句柄发送的信号会发送给整个进程组。
+    ///
+    /// ## 参数
+    ///
+    /// * `output` - 终端输出的投递方式，详见 [OutputDelivery]
+    /// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
+    /// * `size` - 终端窗口大小
+    ///
+    /// ## 返回值
+    ///
+    /// 返回伪终端句柄，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 打开伪终端失败时，返回 [CmdError::Pty] 错误。
+    /// * 无法启动命令时，返回 [CmdError::Execute] 错误。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use tokio::sync::{mpsc, oneshot};
+    /// use wheel_rs::cmd::spawn::{PtySize, SpawnBuilder};
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let (data_sender, mut data_receiver) = mpsc::channel(16);
+    ///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+    ///     let script = "test -t 1 && stty size && head -n 1 >/dev/null && stty size";
+    ///     let pty = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", script]))
+    ///         .spawn_pty(data_sender, process_exit_sender, PtySize { rows: 30, cols: 100 })
+    ///         .unwrap();
+    ///     assert_eq!(data_receiver.recv().await.unwrap(), "30 100\r\n");
+    ///
+    ///     pty.resize(PtySize { rows: 40, cols: 120 }).unwrap();
+    ///     pty.write(b"hello\n").await.unwrap();
+    ///     let mut output = Vec::new();
+    ///     while let Some(data) = data_receiver.recv().await {
+    ///         output.extend_from_slice(&data);
+    ///     }
+    ///     // 终端会回显输入，并将换行转换为回车换行
+    ///     assert_eq!(output, b"hello\r\n40 120\r\n");
+    ///     assert!(process_exit_receiver.await.unwrap().success());
+    /// }
+    /// ```
+    pub fn spawn_pty(
+        &self,
+        output: impl Into<OutputDelivery>,
+        process_exit_sender: oneshot::Sender<CmdExitStatus>,
+        size: PtySize,
+    ) -> Result<PtyHandle, CmdError> {
+        debug!("command execute in pty start: {}", self.command.command_line());
+        let log = self.open_output_log()?;
+        let (master, slave) = open_pty(size)?;
+        let mut command = self
+            .command
+            .clone()
+            .process_group(ProcessGroup::NewSession)
+            .build_command()?;
+        let stdin = slave.try_clone().map_err(CmdError::Pty)?;
+        let stdout = slave.try_clone().map_err(CmdError::Pty)?;
+        command
+            .stdin(Stdio::from(stdin))
+            .stdout(Stdio::from(stdout))
+            .stderr(Stdio::from(slave));
+        // SAFETY: set_controlling_terminal 只调用异步信号安全的 ioctl
+        unsafe {
+            command.pre_exec(set_controlling_terminal);
+        }
+        // 启动后立即释放父进程持有的从设备，子进程全部退出后读取主设备才能结束
+        let child = Command::from(command)
+            .spawn()
+            .map_err(CmdError::Execute)?;
+        let pid = child.id();
+        debug!("command execute in pty started: {:?}", pid);
+
+        // 异步读取终端输出
+        let master = Arc::new(master);
+        let stdout_task = tokio::spawn(read_output(
+            PtyReader(master.clone()),
+            OutputStream::Stdout,
+            OutputSink::from(output.into()),
+            self.read_buffer_size,
+            self.framing,
+            log,
+        ));
+
+        // 异步等待进程退出
+        let (signal_sender, signal_receiver) = mpsc::unbounded_channel();
+        let (reaped_sender, reaped_receiver) = watch::channel(None);
+        let (exit_sender, exit_receiver) = watch::channel(None);
+        tokio::spawn(wait_child(
+            child,
+            true,
+            OutputTasks {
+                stdout: Some(stdout_task),
+                stderr: None,
+            },
+            signal_receiver,
+            reaped_sender,
+            exit_sender,
+            Some(process_exit_sender),
+        ));
+
+        let handle = SpawnHandle::new(pid, signal_sender, None, reaped_receiver, exit_receiver);
+        Ok(PtyHandle::new(handle, master))
+    }
+
+    /// # 启动命令进程作为管道的一个阶段
+    ///
+    /// ## 参数
+    ///
+    /// * `stdin` - 子进程的标准输入
+    /// * `sink` - 输出接收端，标准错误合并时也发送到此接收端
+    /// * `forward_stdout` - 是否将标准输出转发给 `sink`，为 `false` 时返回标准输出管道，
+    ///   由调用者连接到下一个阶段
+    /// * `log` - 输出日志写入器，由 [SpawnBuilder::open_output_log] 打开
+    /// * `process_exit_sender` - 用于发送进程退出状态的通道发送者
+    ///
+    /// ## 返回值
+    ///
+    /// 返回子进程句柄和未被转发的标准输出管道，或者包含错误信息的 [CmdError]。
+    pub(crate) fn spawn_stage(
+        &self,
+        stdin: Stdio,
+        sink: &OutputSink,
+        forward_stdout: bool,
+        log: Option<OutputLogWriter>,
+        process_exit_sender: Option<oneshot::Sender<CmdExitStatus>>,
+    ) -> Result<(SpawnHandle, Option<ChildStdout>), CmdError> {
+        debug!("command execute start: {}", self.command.command_line());
+        let mut child = Command::from(self.command.build_command()?)
+            .stdin(stdin)
+            .stdout(Stdio::piped()) // 将标准输出重定向到管道，以便父进程可以读取
+            .stderr(match self.stderr {
+                StderrMode::Discard => Stdio::null(),
+                StderrMode::Merge | StderrMode::Separate(_) => Stdio::piped(),
+            })
+            .spawn() // 启动命令并返回子进程句柄
+            .map_err(CmdError::Execute)?; // 将可能的错误转换为CmdError类型
+        let pid = child.id();
+        debug!("command execute started: {:?}", pid);
+
+        // 获取标准输入，标准输入未连接到管道时为 `None`
+        let child_stdin = child.stdin.take().map(StdinWriter::new);
+        // 获取标准输出
+        let stdout = child.stdout.take().ok_or(CmdError::TakeStdout())?;
+        // 获取标准错误
+        let stderr_sink = match &self.stderr {
+            StderrMode::Discard => None,
+            StderrMode::Merge => Some(sink.clone()),
+            StderrMode::Separate(sender) => Some(OutputSink::Tagged(sender.clone())),
+        };
+        let stderr_task = match stderr_sink {
+            Some(stderr_sink) => {
+                let stderr = child.stderr.take().ok_or(CmdError::TakeStderr())?;
+                Some(tokio::spawn(read_output(
+                    stderr,
+                    OutputStream::Stderr,
+                    stderr_sink,
+                    self.read_buffer_size,
+                    self.framing,
+                    log.clone(),
+                )))
+            }
+            None => None,
+        };
+
+        // 异步读取输出
+        let (stdout_task, stdout) = if forward_stdout {
+            let stdout_task = tokio::spawn(read_output(
+                stdout,
+                OutputStream::Stdout,
+                sink.clone(),
+                self.read_buffer_size,
+                self.framing,
+                log,
+            ));
+            (Some(stdout_task), None)
+        } else {
+            (None, Some(stdout))
+        };
+
+        // 异步等待进程退出
+        let (signal_sender, signal_receiver) = mpsc::unbounded_channel();
+        let (reaped_sender, reaped_receiver) = watch::channel(None);
+        let (exit_sender, exit_receiver) = watch::channel(None);
+        tokio::spawn(wait_child(
+            child,
+            self.command.get_process_group().is_leader(),
+            OutputTasks {
+                stdout: stdout_task,
+                stderr: stderr_task,
+            },
+            signal_receiver,
+            reaped_sender,
+            exit_sender,
+            process_exit_sender,
+        ));
+
+        let handle = SpawnHandle::new(
+            pid,
+            signal_sender,
+            child_stdin,
+            reaped_receiver,
+            exit_receiver,
+        );
+        Ok((handle, stdout))
+    }
+}
+
+/// # 收集子进程的输出并等待其退出
+///
+/// 同时读取标准输出和标准错误直到 EOF，然后等待子进程退出。
+///
+/// ## 参数
+///
+/// * `child` - 子进程
+/// * `stdout` - 子进程的标准输出管道
+/// * `stderr` - 子进程的标准错误管道
+/// * `max_output_size` - 标准输出和标准错误各自的大小上限
+async fn collect_output(
+    child: &mut Child,
+    stdout: ChildStdout,
+    stderr: ChildStderr,
+    max_output_size: Option<usize>,
+) -> Result<(ExitStatus, Vec<u8>, Vec<u8>), CmdError> {
+    let (stdout, stderr) = tokio::try_join!(
+        read_to_end(stdout, max_output_size),
+        read_to_end(stderr, max_output_size)
+    )?;
+    let status = child.wait().await.map_err(CmdError::Execute)?;
+    Ok((status, stdout, stderr))
+}
+
+/// # 读取管道的全部内容
+///
+/// 读取的内容超过大小上限时返回 [CmdError::OutputTooLarge] 错误。
+async fn read_to_end<R: AsyncRead + Unpin>(
+    mut reader: R,
+    max_output_size: Option<usize>,
+) -> Result<Vec<u8>, CmdError> {
+    let mut buffer = Vec::new();
+    let Some(max_output_size) = max_output_size else {
+        reader
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(CmdError::Execute)?;
+        return Ok(buffer);
+    };
+    // 多读取一个字节，用于判断是否超过大小上限
+    reader
+        .take(max_output_size as u64 + 1)
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(CmdError::Execute)?;
+    if buffer.len() > max_output_size {
+        return Err(CmdError::OutputTooLarge(max_output_size));
+    }
+    Ok(buffer)
+}
+
+/// # 读取子进程输出的任务
+struct OutputTasks {
+    /// 读取标准输出的任务，标准输出连接到下一个阶段时为 `None`
+    stdout: Option<JoinHandle<io::Result<()>>>,
+    /// 读取标准错误的任务，标准错误被丢弃或与标准输出共用伪终端时为 `None`
+    stderr: Option<JoinHandle<io::Result<()>>>,
+}
+
+/// # 等待子进程退出
+///
+/// 等待子进程退出并立即回收，然后等待标准输出和标准错误都读取结束，最后发布最终的退出状态，
+/// 保证调用者收到退出状态时，子进程的输出都已经转发完毕。
+/// 回收之前处理句柄发来的信号请求，回收之后关闭请求通道，保证信号不会发给复用了进程ID的无关进程。
+///
+/// ## 参数
+///
+/// * `child` - 子进程
+/// * `group_leader` - 子进程是否为新进程组的组长，是时信号发送给整个进程组
+/// * `output_tasks` - 读取标准输出和标准错误的任务
+/// * `signal_receiver` - 接收句柄发来的信号请求的通道
+/// * `reaped_sender` - 用于向句柄发布子进程被回收时的退出状态的发送者
+/// * `exit_sender` - 用于向句柄发布退出状态的发送者
+/// * `process_exit_sender` - 用于向调用者发送退出状态的通道发送者，可以为 `None`
+async fn wait_child(
+    mut child: Child,
+    group_leader: bool,
+    output_tasks: OutputTasks,
+    mut signal_receiver: mpsc::UnboundedReceiver<SignalRequest>,
+    reaped_sender: watch::Sender<Option<CmdExitStatus>>,
+    exit_sender: watch::Sender<Option<CmdExitStatus>>,
+    process_exit_sender: Option<oneshot::Sender<CmdExitStatus>>,
+) {
+    let status = loop {
+        tokio::select! {
+            status = child.wait() => break status,
+            Some(request) = signal_receiver.recv() => {
+                let result = signal_child(&child, group_leader, request.signal);
+                if let Some(reply) = request.reply {
+                    let _ = reply.send(result);
+                }
+            }
+        }
+    };
+    drop(signal_receiver);
+    let status = status
+        .inspect_err(|e| error!("wait command process error: {:#}", e))
+        .ok();
+    reaped_sender.send_replace(Some(CmdExitStatus::new(status, false)));
+    debug!("command process exited: {:?}", status);
+
+    let stdout_error = match output_tasks.stdout {
+        Some(stdout_task) => !matches!(stdout_task.await, Ok(Ok(()))),
+        None => false,
+    };
+    if let Some(stderr_task) = output_tasks.stderr {
+        let _ = stderr_task.await;
+    }
+    let exit_status = CmdExitStatus::new(status, stdout_error);
+    exit_sender.send_replace(Some(exit_status));
+    if let Some(process_exit_sender) = process_exit_sender {
+        let _ = process_exit_sender.send(exit_status);
+    }
+}
+
+/// # 向尚未回收的子进程发送信号
+///
+/// 子进程尚未被回收，其进程ID以及以其为ID的进程组都不会被复用。
+/// 进程或进程组已不存在时不视为错误。
+fn signal_child(child: &Child, group_leader: bool, signal: Signal) -> Result<(), CmdError> {
+    let pid = child.id().ok_or(CmdError::EmptyId)?;
+    let result = if group_leader {
+        kill_process_group(pid, signal)
+    } else {
+        kill_process_by_id(pid, signal)
+    };
+    match result {
+        Err(CmdError::NoSuchProcess(_)) => Ok(()),
+        result => result,
+    }
+}
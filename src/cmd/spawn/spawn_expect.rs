@@ -0,0 +1,497 @@
+//! # 脚本化交互模块
+//!
+//! 在子进程的输出流之上提供类似 `expect` 的交互能力：等待输出匹配指定的正则表达式，
+//! 然后发送响应。多个步骤可以通过 [ExpectScript] 串联成脚本，
+//! 用于自动化需要确认提示的工具，无需在每个服务中手动扫描输出缓冲区。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::output_backlog::BacklogReceiver;
+use crate::cmd::spawn::spawn_pty::PtyHandle;
+use crate::cmd::spawn::spawn_stdin::StdinWriter;
+use bytes::Bytes;
+use regex::Regex;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{Instant, timeout_at};
+use tracing::{debug, warn};
+
+/// 默认的交互输出缓冲区大小上限（1 MiB）
+const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// # 交互输入端
+///
+/// 向子进程发送响应的目标。
+#[derive(Debug, Clone)]
+pub enum ExpectInput {
+    /// 子进程的标准输入
+    Stdin(StdinWriter),
+    /// 子进程所在的伪终端
+    Pty(PtyHandle),
+}
+
+impl From<StdinWriter> for ExpectInput {
+    fn from(writer: StdinWriter) -> Self {
+        ExpectInput::Stdin(writer)
+    }
+}
+
+impl From<PtyHandle> for ExpectInput {
+    fn from(pty: PtyHandle) -> Self {
+        ExpectInput::Pty(pty)
+    }
+}
+
+/// # 交互输出端
+///
+/// 接收子进程输出的来源，与 [OutputDelivery](crate::cmd::spawn::OutputDelivery) 的投递方式对应。
+#[derive(Debug)]
+pub enum ExpectOutput {
+    /// 广播接收者，落后时丢失的数据会被跳过
+    Broadcast(broadcast::Receiver<Bytes>),
+    /// 有界通道接收者
+    Channel(mpsc::Receiver<Bytes>),
+    /// 输出积压缓冲区的接收者，先接收积压的输出，落后时丢失的数据会被跳过
+    Backlog(BacklogReceiver),
+}
+
+impl From<broadcast::Receiver<Bytes>> for ExpectOutput {
+    fn from(receiver: broadcast::Receiver<Bytes>) -> Self {
+        ExpectOutput::Broadcast(receiver)
+    }
+}
+
+impl From<mpsc::Receiver<Bytes>> for ExpectOutput {
+    fn from(receiver: mpsc::Receiver<Bytes>) -> Self {
+        ExpectOutput::Channel(receiver)
+    }
+}
+
+impl From<BacklogReceiver> for ExpectOutput {
+    fn from(receiver: BacklogReceiver) -> Self {
+        ExpectOutput::Backlog(receiver)
+    }
+}
+
+impl ExpectOutput {
+    /// # 接收下一块输出
+    ///
+    /// 输出结束时返回 `None`。
+    async fn recv(&mut self) -> Option<Bytes> {
+        match self {
+            ExpectOutput::Broadcast(receiver) => loop {
+                match receiver.recv().await {
+                    Ok(data) => return Some(data),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        warn!("expect output lagged, {count} messages skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            },
+            ExpectOutput::Channel(receiver) => receiver.recv().await,
+            ExpectOutput::Backlog(receiver) => loop {
+                match receiver.recv().await {
+                    Ok(data) => return Some(data),
+                    Err(broadcast::error::RecvError::Lagged(count)) => {
+                        warn!("expect output lagged, {count} messages skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            },
+        }
+    }
+}
+
+/// # 匹配结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectMatch {
+    /// 匹配位置之前的输出
+    pub before: String,
+    /// 匹配到的文本
+    pub matched: String,
+    /// 捕获组，未参与匹配的捕获组为 `None`，不包含整个匹配
+    pub groups: Vec<Option<String>>,
+}
+
+/// # 交互会话
+///
+/// 持有子进程的输入端和输出端，以及尚未被匹配消耗的输出。输出按 UTF-8 解码后进行匹配，
+/// 非法的字节序列会被替换为 `U+FFFD`。未被匹配消耗的输出超过缓冲区大小上限时，
+/// 最早的文本会被丢弃，避免子进程持续输出而一直不匹配时占用的内存无限增长。
+///
+/// ## 示例
+///
+/// ```
+/// use regex::Regex;
+/// use std::time::Duration;
+/// use tokio::sync::{mpsc, oneshot};
+/// use wheel_rs::cmd::spawn::{Expect, SpawnBuilder};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let script = "printf 'Continue? [y/N] '; read answer; echo \"answer: $answer\"";
+///     let (data_sender, data_receiver) = mpsc::channel(16);
+///     let (process_exit_sender, _) = oneshot::channel();
+///     let handle = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", script]))
+///         .stdin_writer()
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///
+///     let mut session = Expect::new(handle.stdin().unwrap().clone(), data_receiver);
+///     let timeout = Duration::from_secs(5);
+///     session.expect(&Regex::new(r"\[y/N\]").unwrap(), timeout).await.unwrap();
+///     session.send("y\n").await.unwrap();
+///     let matched = session
+///         .expect(&Regex::new(r"answer: (\w+)").unwrap(), timeout)
+///         .await
+///         .unwrap();
+///     assert_eq!(matched.groups, [Some("y".to_string())]);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Expect {
+    /// 输入端
+    input: ExpectInput,
+    /// 输出端
+    output: ExpectOutput,
+    /// 尚未被匹配消耗的输出
+    buffer: ExpectBuffer,
+}
+
+impl Expect {
+    /// # 创建交互会话
+    ///
+    /// ## 参数
+    ///
+    /// * `input` - 发送响应的目标，可以直接传入 [StdinWriter] 或 [PtyHandle]
+    /// * `output` - 子进程输出的接收者，可以直接传入广播接收者、有界通道接收者或积压缓冲区接收者
+    pub fn new(input: impl Into<ExpectInput>, output: impl Into<ExpectOutput>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            buffer: ExpectBuffer::default(),
+        }
+    }
+
+    /// # 设置缓冲区大小上限
+    ///
+    /// 未被匹配消耗的输出超过上限（按字节计算）时丢弃最早的文本，默认为 1 MiB。
+    /// 上限需要大于要匹配的文本长度，否则匹配可能因为开头被丢弃而失败。
+    pub fn max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        self.buffer.max_size = max_buffer_size;
+        self
+    }
+
+    /// # 等待输出匹配正则表达式
+    ///
+    /// 在已接收的输出中查找匹配，找不到时继续接收输出，直到匹配成功、输出结束或超时。
+    /// 匹配成功后，匹配位置及之前的输出会被消耗，后续的匹配从匹配位置之后开始。
+    ///
+    /// ## 参数
+    ///
+    /// * `pattern` - 要匹配的正则表达式
+    /// * `timeout` - 等待匹配的超时时间
+    ///
+    /// ## 返回值
+    ///
+    /// 返回匹配结果 [ExpectMatch]，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 超时仍未匹配时，返回 [CmdError::ExpectTimeout] 错误。
+    /// * 输出结束仍未匹配时，返回 [CmdError::ExpectEof] 错误。
+    pub async fn expect(
+        &mut self,
+        pattern: &Regex,
+        timeout: Duration,
+    ) -> Result<ExpectMatch, CmdError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(matched) = self.buffer.take_match(pattern) {
+                debug!("expect matched: {:?}", matched.matched);
+                return Ok(matched);
+            }
+            match timeout_at(deadline, self.output.recv()).await {
+                Ok(Some(data)) => self.buffer.push(&data),
+                Ok(None) => return Err(CmdError::ExpectEof(pattern.to_string())),
+                Err(_) => return Err(CmdError::ExpectTimeout(pattern.to_string(), timeout)),
+            }
+        }
+    }
+
+    /// # 发送数据
+    ///
+    /// 向子进程发送响应，如 `"y\n"`。
+    ///
+    /// ## 错误处理
+    ///
+    /// 写入失败时返回相应的 [CmdError]。
+    pub async fn send(&self, data: impl AsRef<[u8]>) -> Result<(), CmdError> {
+        match &self.input {
+            ExpectInput::Stdin(writer) => writer.write(data).await,
+            ExpectInput::Pty(pty) => pty.write(data.as_ref()).await,
+        }
+    }
+
+    /// # 获取尚未被匹配消耗的输出
+    pub fn buffer(&self) -> &str {
+        &self.buffer.text
+    }
+}
+
+/// # 脚本步骤
+#[derive(Debug, Clone)]
+enum ExpectStep {
+    /// 等待输出匹配正则表达式
+    Expect(Regex, Duration),
+    /// 发送数据
+    Send(Bytes),
+}
+
+/// # 交互脚本
+///
+/// 将多个“等待匹配”和“发送响应”的步骤串联起来，按顺序在交互会话上执行。
+///
+/// ## 示例
+///
+/// ```
+/// use regex::Regex;
+/// use std::time::Duration;
+/// use tokio::sync::{mpsc, oneshot};
+/// use wheel_rs::cmd::spawn::{Expect, ExpectScript, SpawnBuilder};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let installer = "printf 'Name: '; read name; printf 'Install? [y/N] '; read ok; \
+///                      test \"$ok\" = y && echo \"installed for $name\"";
+///     let (data_sender, data_receiver) = mpsc::channel(16);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     let handle = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", installer]))
+///         .stdin_writer()
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///
+///     let mut session = Expect::new(handle.stdin().unwrap().clone(), data_receiver);
+///     let matches = ExpectScript::new(Duration::from_secs(5))
+///         .expect(Regex::new("Name: ").unwrap())
+///         .send("alice\n")
+///         .expect(Regex::new(r"\[y/N\]").unwrap())
+///         .send("y\n")
+///         .expect(Regex::new(r"installed for (\w+)").unwrap())
+///         .run(&mut session)
+///         .await
+///         .unwrap();
+///     assert_eq!(matches[2].groups, [Some("alice".to_string())]);
+///     assert!(process_exit_receiver.await.unwrap().success());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExpectScript {
+    /// 脚本步骤
+    steps: Vec<ExpectStep>,
+    /// 默认的等待匹配超时时间
+    timeout: Duration,
+}
+
+impl ExpectScript {
+    /// # 创建交互脚本
+    ///
+    /// ## 参数
+    ///
+    /// * `timeout` - 每个等待匹配步骤默认的超时时间
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            steps: Vec::new(),
+            timeout,
+        }
+    }
+
+    /// # 添加等待匹配步骤
+    ///
+    /// 使用默认的超时时间。
+    pub fn expect(self, pattern: Regex) -> Self {
+        let timeout = self.timeout;
+        self.expect_timeout(pattern, timeout)
+    }
+
+    /// # 添加指定超时时间的等待匹配步骤
+    pub fn expect_timeout(mut self, pattern: Regex, timeout: Duration) -> Self {
+        self.steps.push(ExpectStep::Expect(pattern, timeout));
+        self
+    }
+
+    /// # 添加发送数据步骤
+    pub fn send(mut self, data: impl Into<Bytes>) -> Self {
+        self.steps.push(ExpectStep::Send(data.into()));
+        self
+    }
+
+    /// # 执行脚本
+    ///
+    /// 按顺序执行所有步骤，任意一个步骤失败时立即返回错误。
+    ///
+    /// ## 返回值
+    ///
+    /// 返回每个等待匹配步骤的匹配结果，或者包含错误信息的 [CmdError]。
+    pub async fn run(&self, session: &mut Expect) -> Result<Vec<ExpectMatch>, CmdError> {
+        let mut matches = Vec::new();
+        for step in &self.steps {
+            match step {
+                ExpectStep::Expect(pattern, timeout) => {
+                    matches.push(session.expect(pattern, *timeout).await?);
+                }
+                ExpectStep::Send(data) => session.send(data).await?,
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// # 交互输出缓冲区
+///
+/// 将接收到的字节增量解码为 UTF-8 文本，跨数据块被截断的字符会等待后续数据补全。
+/// 文本超过大小上限时丢弃最早的部分。
+#[derive(Debug)]
+struct ExpectBuffer {
+    /// 已解码的文本
+    text: String,
+    /// 尚未解码的不完整字节序列
+    pending: Vec<u8>,
+    /// 已解码文本的大小上限
+    max_size: usize,
+}
+
+impl Default for ExpectBuffer {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            pending: Vec::new(),
+            max_size: DEFAULT_MAX_BUFFER_SIZE,
+        }
+    }
+}
+
+impl ExpectBuffer {
+    /// # 追加数据
+    fn push(&mut self, data: &[u8]) {
+        self.pending.extend_from_slice(data);
+        let mut start = 0;
+        loop {
+            match std::str::from_utf8(&self.pending[start..]) {
+                Ok(text) => {
+                    self.text.push_str(text);
+                    start = self.pending.len();
+                    break;
+                }
+                Err(e) => {
+                    let valid = start + e.valid_up_to();
+                    // SAFETY: valid_up_to 之前的字节是合法的 UTF-8
+                    self.text.push_str(unsafe {
+                        std::str::from_utf8_unchecked(&self.pending[start..valid])
+                    });
+                    match e.error_len() {
+                        Some(len) => {
+                            self.text.push(char::REPLACEMENT_CHARACTER);
+                            start = valid + len;
+                        }
+                        // 不完整的字符，等待后续数据
+                        None => {
+                            start = valid;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending.drain(..start);
+        self.trim();
+    }
+
+    /// # 丢弃超过大小上限的最早文本
+    ///
+    /// 从字符边界处截断，保留的文本不超过大小上限。
+    fn trim(&mut self) {
+        if self.text.len() <= self.max_size {
+            return;
+        }
+        let mut start = self.text.len() - self.max_size;
+        while !self.text.is_char_boundary(start) {
+            start += 1;
+        }
+        debug!(
+            "expect buffer exceeds {} bytes, {start} bytes dropped",
+            self.max_size
+        );
+        self.text.drain(..start);
+    }
+
+    /// # 查找并消耗匹配
+    fn take_match(&mut self, pattern: &Regex) -> Option<ExpectMatch> {
+        let captures = pattern.captures(&self.text)?;
+        let whole = captures.get(0)?;
+        let matched = ExpectMatch {
+            before: self.text[..whole.start()].to_string(),
+            matched: whole.as_str().to_string(),
+            groups: captures
+                .iter()
+                .skip(1)
+                .map(|group| group.map(|group| group.as_str().to_string()))
+                .collect(),
+        };
+        let end = whole.end();
+        self.text.drain(..end);
+        Some(matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_match_consumes_output() {
+        let mut buffer = ExpectBuffer::default();
+        buffer.push(b"login: ");
+        buffer.push(b"password: ");
+        let pattern = Regex::new(r"(\w+): ").unwrap();
+        let matched = buffer.take_match(&pattern).unwrap();
+        assert_eq!(matched.before, "");
+        assert_eq!(matched.groups, [Some("login".to_string())]);
+        let matched = buffer.take_match(&pattern).unwrap();
+        assert_eq!(matched.groups, [Some("password".to_string())]);
+        assert!(buffer.take_match(&pattern).is_none());
+    }
+
+    #[test]
+    fn test_buffer_split_utf8_character() {
+        let mut buffer = ExpectBuffer::default();
+        let data = "继续？".as_bytes();
+        buffer.push(&data[..4]);
+        assert_eq!(buffer.text, "继");
+        buffer.push(&data[4..]);
+        assert_eq!(buffer.text, "继续？");
+        assert!(buffer.pending.is_empty());
+    }
+
+    #[test]
+    fn test_buffer_trim_oldest_text() {
+        let mut buffer = ExpectBuffer {
+            max_size: 4,
+            ..ExpectBuffer::default()
+        };
+        buffer.push(b"012345");
+        assert_eq!(buffer.text, "2345");
+        // 截断位置落在多字节字符中间时，整个字符都被丢弃
+        buffer.push("继续".as_bytes());
+        assert_eq!(buffer.text, "续");
+    }
+
+    #[test]
+    fn test_buffer_invalid_utf8() {
+        let mut buffer = ExpectBuffer::default();
+        buffer.push(b"a\xffb");
+        assert_eq!(buffer.text, "a\u{fffd}b");
+        assert!(buffer.pending.is_empty());
+    }
+}
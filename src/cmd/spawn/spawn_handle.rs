@@ -0,0 +1,355 @@
+//! # 子进程句柄模块
+//!
+//! 提供 [SpawnHandle] 结构体和 [CmdExitStatus] 结构体。
+//!
+//! 通过 [SpawnBuilder](crate::cmd::spawn::SpawnBuilder) 启动的子进程由后台任务持有并回收，
+//! 调用者通过句柄查询进程状态、发送信号以及等待进程退出。信号也由后台任务在回收子进程之前发送，
+//! 子进程被回收后进程ID可能被复用，此时不会再发送任何信号。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::spawn_stdin::StdinWriter;
+use crate::process::parse_signal_instruction;
+use nix::sys::signal::Signal;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+/// # 命令退出状态
+///
+/// 子进程被回收后的退出信息。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CmdExitStatus {
+    /// 进程退出码，进程被信号终止或无法获取退出状态时为 `None`
+    pub code: Option<i32>,
+    /// 终止进程的信号编号，进程正常退出时为 `None`
+    pub signal: Option<i32>,
+    /// 读取标准输出时是否发生错误
+    pub stdout_error: bool,
+}
+
+impl CmdExitStatus {
+    /// # 根据进程退出状态创建命令退出状态
+    pub(crate) fn new(status: Option<ExitStatus>, stdout_error: bool) -> Self {
+        Self {
+            code: status.and_then(|status| status.code()),
+            signal: status.and_then(|status| status.signal()),
+            stdout_error,
+        }
+    }
+
+    /// # 命令是否执行成功
+    ///
+    /// 仅当进程正常退出、退出码为 0 且读取标准输出未发生错误时返回 `true`。
+    pub fn success(&self) -> bool {
+        self.code == Some(0) && !self.stdout_error
+    }
+}
+
+/// # 进程停止阶段
+///
+/// 优雅地停止进程时，标识进程是在哪个阶段结束的。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopStage {
+    /// 停止前进程已经退出
+    AlreadyExited,
+    /// 进程在宽限期内响应首个信号退出
+    Graceful,
+    /// 进程在宽限期内未退出，被 `SIGKILL` 强制杀死
+    Killed,
+}
+
+/// # 信号请求
+///
+/// 发送给持有子进程的后台任务，由后台任务在回收子进程之前发送信号。
+#[derive(Debug)]
+pub(crate) struct SignalRequest {
+    /// 要发送的信号
+    pub(crate) signal: Signal,
+    /// 用于返回发送结果的通道发送者，为 `None` 时不关心发送结果
+    pub(crate) reply: Option<oneshot::Sender<Result<(), CmdError>>>,
+}
+
+/// # 子进程句柄
+///
+/// 子进程本身由后台任务持有，后台任务在进程退出后立即回收进程，
+/// 并在标准输出和标准错误都读取结束后发布最终的 [CmdExitStatus]。
+/// 句柄可以被克隆，所有克隆共享同一个子进程的状态。
+#[derive(Debug, Clone)]
+pub struct SpawnHandle {
+    /// 子进程ID
+    pid: Option<u32>,
+    /// 向后台任务发送信号请求的通道
+    signal_sender: mpsc::UnboundedSender<SignalRequest>,
+    /// 子进程的标准输入写入器
+    stdin: Option<StdinWriter>,
+    /// 子进程被回收时的退出状态，尚未回收时为 `None`
+    reaped_receiver: watch::Receiver<Option<CmdExitStatus>>,
+    /// 子进程的最终退出状态
+    exit_receiver: watch::Receiver<Option<CmdExitStatus>>,
+}
+
+impl SpawnHandle {
+    /// # 创建子进程句柄
+    pub(crate) fn new(
+        pid: Option<u32>,
+        signal_sender: mpsc::UnboundedSender<SignalRequest>,
+        stdin: Option<StdinWriter>,
+        reaped_receiver: watch::Receiver<Option<CmdExitStatus>>,
+        exit_receiver: watch::Receiver<Option<CmdExitStatus>>,
+    ) -> Self {
+        Self {
+            pid,
+            signal_sender,
+            stdin,
+            reaped_receiver,
+            exit_receiver,
+        }
+    }
+
+    /// # 获取子进程ID
+    pub fn id(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// # 获取子进程的标准输入写入器
+    ///
+    /// 仅当通过 [SpawnBuilder::stdin_writer](crate::cmd::spawn::SpawnBuilder::stdin_writer)
+    /// 启用了标准输入写入器时返回 `Some`。
+    pub fn stdin(&self) -> Option<&StdinWriter> {
+        self.stdin.as_ref()
+    }
+
+    /// # 检查进程是否还活着
+    ///
+    /// 子进程尚未被回收时返回 `true`。
+    pub fn is_alive(&self) -> bool {
+        self.reaped_receiver.borrow().is_none()
+    }
+
+    /// # 获取进程的最终退出状态
+    ///
+    /// 进程尚未退出或标准输出尚未读取结束时返回 `None`。
+    pub fn exit_status(&self) -> Option<CmdExitStatus> {
+        *self.exit_receiver.borrow()
+    }
+
+    /// # 等待进程退出
+    ///
+    /// 等待子进程被回收且标准输出和标准错误都读取结束，返回最终的退出状态。
+    /// 注意：如果子进程派生的后台进程继承并一直持有标准输出或标准错误，则会一直等待到其关闭为止。
+    ///
+    /// ## 错误处理
+    ///
+    /// 如果后台任务异常结束而未发布退出状态，则返回 [CmdError::Wait] 错误。
+    pub async fn wait(&self) -> Result<CmdExitStatus, CmdError> {
+        let mut exit_receiver = self.exit_receiver.clone();
+        let status = exit_receiver.wait_for(Option::is_some).await.map_err(|_| {
+            CmdError::Wait(io::Error::other(
+                "command process exit status is unavailable",
+            ))
+        })?;
+        Ok(status.expect("exit status should be present"))
+    }
+
+    /// # 等待进程被回收
+    ///
+    /// 与 [SpawnHandle::wait] 不同，不等待标准输出读取结束，返回的退出状态不包含读取标准输出的结果。
+    pub(crate) async fn wait_reaped(&self) -> Result<CmdExitStatus, CmdError> {
+        let mut reaped_receiver = self.reaped_receiver.clone();
+        let status = reaped_receiver
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| {
+                CmdError::Wait(io::Error::other(
+                    "command process reaped state is unavailable",
+                ))
+            })?;
+        Ok(status.expect("reaped status should be present"))
+    }
+
+    /// # 向进程发送信号
+    ///
+    /// 信号由持有子进程的后台任务发送。子进程是新进程组的组长时，信号会发送给整个进程组；
+    /// 子进程已被回收时直接返回 `Ok(())`，不会再发送信号，因为进程ID（以及同名的进程组ID）可能已被复用。
+    /// 需要清理组长退出后仍在运行的后代进程时，可以显式调用
+    /// [kill_process_group](crate::cmd::std::kill_process_group)，由调用者确认进程组没有被复用。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 如果无法获取进程ID，则返回 [CmdError::EmptyId] 错误。
+    /// * 如果发送信号失败，则返回 [kill_process_by_id](crate::cmd::std::kill_process_by_id) 或
+    ///   [kill_process_group](crate::cmd::std::kill_process_group) 返回的错误，进程或进程组已不存在时不视为错误。
+    pub async fn signal(&self, signal: Signal) -> Result<(), CmdError> {
+        self.send_signal(signal).await.map(|_| ())
+    }
+
+    /// # 请求后台任务发送信号并等待结果
+    ///
+    /// 信号已发送时返回 `Ok(true)`，子进程在发送之前已被回收时返回 `Ok(false)`。
+    async fn send_signal(&self, signal: Signal) -> Result<bool, CmdError> {
+        self.pid.ok_or(CmdError::EmptyId)?;
+        let (reply_sender, reply_receiver) = oneshot::channel();
+        let request = SignalRequest {
+            signal,
+            reply: Some(reply_sender),
+        };
+        // 后台任务回收子进程后关闭通道，未处理的请求随之被丢弃
+        if self.signal_sender.send(request).is_err() {
+            return Ok(false);
+        }
+        match reply_receiver.await {
+            Ok(result) => result.map(|_| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// # 请求后台任务发送信号，不等待结果
+    ///
+    /// 用于无法等待的同步上下文，如启动管道失败时清理已启动的阶段。
+    pub(crate) fn start_signal(&self, signal: Signal) {
+        let _ = self.signal_sender.send(SignalRequest {
+            signal,
+            reply: None,
+        });
+    }
+
+    /// # 杀死进程
+    ///
+    /// 向进程（或其所在的整个进程组）发送 `SIGKILL` 信号，并等待其被回收。
+    /// 进程已被回收时不发送信号。
+    ///
+    /// ## 错误处理
+    ///
+    /// 如果发送信号或等待进程回收失败，则返回相应的 [CmdError]。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use tokio::sync::{broadcast, oneshot};
+    /// use wheel_rs::cmd::process_group::ProcessGroup;
+    /// use wheel_rs::cmd::spawn::SpawnBuilder;
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let (data_sender, _) = broadcast::channel(16);
+    ///     let (process_exit_sender, _) = oneshot::channel();
+    ///     let command = CmdBuilder::new("sh")
+    ///         .args(["-c", "sleep 10 | sleep 10"])
+    ///         .process_group(ProcessGroup::NewSession);
+    ///     let handle = SpawnBuilder::new(command)
+    ///         .spawn(data_sender, process_exit_sender)
+    ///         .unwrap();
+    ///     handle.kill().await.unwrap();
+    ///     // 管道中的每个进程都已被杀死，标准输出随之关闭
+    ///     let exit_status = handle.wait().await.unwrap();
+    ///     assert_eq!(exit_status.signal, Some(9));
+    /// }
+    /// ```
+    pub async fn kill(&self) -> Result<(), CmdError> {
+        self.signal(Signal::SIGKILL).await?;
+        self.wait_reaped().await.map(|_| ())
+    }
+
+    /// # 优雅地停止进程
+    ///
+    /// 先按指令向进程发送信号，在宽限期内等待其退出；超过宽限期仍未退出时，
+    /// 升级为 `SIGKILL` 强制杀死进程。返回时进程已被回收，但标准输出可能尚未读取结束。
+    /// 子进程是新进程组的组长时，每个阶段的信号都会发送给整个进程组。
+    /// 发送信号之前进程已被回收时，返回 [StopStage::AlreadyExited]。
+    ///
+    /// ## 参数
+    ///
+    /// * `instruction` - 首先发送的信号指令，支持的指令见
+    ///   [send_signal_by_instruction](crate::process::send_signal_by_instruction)
+    /// * `grace_period` - 发送信号后等待进程退出的宽限期
+    ///
+    /// ## 返回值
+    ///
+    /// 返回结束进程的阶段 [StopStage]，或者包含错误信息的 [CmdError]。
+    pub async fn stop(
+        &self,
+        instruction: &str,
+        grace_period: Duration,
+    ) -> Result<StopStage, CmdError> {
+        if !self.is_alive() {
+            return Ok(StopStage::AlreadyExited);
+        }
+        let pid = self.pid.ok_or(CmdError::EmptyId)?;
+        debug!("stopping command process: {pid}");
+        let signal = parse_signal_instruction(instruction)?;
+        if !self.send_signal(signal).await? {
+            return Ok(StopStage::AlreadyExited);
+        }
+        if timeout(grace_period, self.wait_reaped()).await.is_ok() {
+            return Ok(StopStage::Graceful);
+        }
+        warn!("command process did not exit within {grace_period:?}, killing: {pid}");
+        self.kill().await?;
+        Ok(StopStage::Killed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::process_group::ProcessGroup;
+    use crate::cmd::spawn::{OutputStream, SpawnBuilder, StderrMode};
+    use crate::cmd::std::{CmdBuilder, kill_process_group};
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    use tokio::sync::broadcast;
+
+    /// 测试组长已被回收后不再向进程组发送信号，进程组ID可能已被复用
+    #[tokio::test]
+    async fn test_stop_reaped_group_leader() {
+        let (data_sender, mut data_receiver) = broadcast::channel(16);
+        let (process_exit_sender, _) = oneshot::channel();
+        // 组长立即退出，后台进程留在同一个进程组中
+        let command = CmdBuilder::new("sh")
+            .args(["-c", "sleep 10 & echo $!"])
+            .process_group(ProcessGroup::NewGroup);
+        let handle = SpawnBuilder::new(command)
+            .spawn(data_sender, process_exit_sender)
+            .unwrap();
+        let output = data_receiver.recv().await.unwrap();
+        let background: i32 = String::from_utf8_lossy(&output).trim().parse().unwrap();
+        handle.wait_reaped().await.unwrap();
+
+        assert!(!handle.send_signal(Signal::SIGTERM).await.unwrap());
+        handle.signal(Signal::SIGKILL).await.unwrap();
+        handle.kill().await.unwrap();
+        let stage = handle
+            .stop("terminate", Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(stage, StopStage::AlreadyExited);
+        assert!(kill(Pid::from_raw(background), None).is_ok());
+
+        kill_process_group(handle.id().unwrap(), Signal::SIGKILL).unwrap();
+    }
+
+    /// 测试标准错误读取结束后才发布退出状态，即使标准输出已经提前关闭
+    #[tokio::test]
+    async fn test_wait_for_stderr_reader() {
+        let (data_sender, _) = broadcast::channel(16);
+        let (stderr_sender, mut stderr_receiver) = broadcast::channel(16);
+        let (process_exit_sender, _) = oneshot::channel();
+        // 后台进程只继承标准错误，在进程退出之后才输出
+        let command =
+            CmdBuilder::new("sh").args(["-c", "(sleep 1; echo late >&2) >/dev/null & exit 0"]);
+        let handle = SpawnBuilder::new(command)
+            .stderr(StderrMode::Separate(stderr_sender))
+            .spawn(data_sender, process_exit_sender)
+            .unwrap();
+        assert!(handle.wait().await.unwrap().success());
+
+        let chunk = stderr_receiver.try_recv().unwrap();
+        assert_eq!(chunk.stream, OutputStream::Stderr);
+        assert_eq!(chunk.data, "late\n");
+    }
+}
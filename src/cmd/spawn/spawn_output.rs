@@ -0,0 +1,226 @@
+//! # 子进程输出模块
+//!
+//! 定义子进程输出的来源标记、标准错误的处理方式，以及将子进程管道输出
+//! 异步转发给订阅者的读取循环。
+
+use crate::cmd::spawn::output_backlog::{BacklogWriter, OutputBacklog};
+use crate::cmd::spawn::output_framing::{FrameDecoder, OutputFraming};
+use crate::cmd::spawn::output_log::OutputLogWriter;
+use bytes::Bytes;
+use std::fmt::Display;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+use tokio::sync::broadcast::Sender;
+use tokio::sync::mpsc;
+use tracing::{debug, error, trace, warn};
+
+/// # 输出流
+///
+/// 标记一段输出数据来自子进程的哪个输出流。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    /// 标准输出
+    Stdout,
+    /// 标准错误
+    Stderr,
+}
+
+impl Display for OutputStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputStream::Stdout => write!(f, "stdout"),
+            OutputStream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+/// # 带来源标记的输出数据块
+#[derive(Debug, Clone)]
+pub struct OutputChunk {
+    /// 数据来源的输出流
+    pub stream: OutputStream,
+    /// 输出数据
+    pub data: Bytes,
+}
+
+/// # 标准错误处理方式
+///
+/// 决定子进程标准错误输出的去向。
+#[derive(Debug, Clone, Default)]
+pub enum StderrMode {
+    /// 丢弃标准错误输出
+    #[default]
+    Discard,
+    /// 与标准输出合并，发送到同一个输出接收端
+    Merge,
+    /// 转发到单独的广播发送者，每个数据块都带有 [OutputStream::Stderr] 标记
+    Separate(Sender<OutputChunk>),
+}
+
+/// # 输出投递方式
+///
+/// 决定子进程输出如何投递给消费者。
+#[derive(Debug, Clone)]
+pub enum OutputDelivery {
+    /// 广播给所有订阅者
+    ///
+    /// 没有订阅者时数据会被丢弃，落后的订阅者会丢失消息，适用于实时查看等允许丢失数据的场景。
+    Broadcast(Sender<Bytes>),
+    /// 通过有界通道投递给单个消费者
+    ///
+    /// 通道写满时读取任务会暂停读取，子进程写满管道缓冲区后会被阻塞，从而形成背压，
+    /// 保证消费者收到每一个字节。消费者关闭通道后，剩余的输出会被丢弃。
+    Channel(mpsc::Sender<Bytes>),
+    /// 写入输出积压缓冲区，并广播给其订阅者
+    ///
+    /// 缓冲区保留最近的输出，中途订阅的消费者也能收到订阅之前的输出，详见 [OutputBacklog]。
+    Backlog(BacklogWriter),
+}
+
+impl From<Sender<Bytes>> for OutputDelivery {
+    fn from(sender: Sender<Bytes>) -> Self {
+        OutputDelivery::Broadcast(sender)
+    }
+}
+
+impl From<mpsc::Sender<Bytes>> for OutputDelivery {
+    fn from(sender: mpsc::Sender<Bytes>) -> Self {
+        OutputDelivery::Channel(sender)
+    }
+}
+
+impl From<OutputBacklog> for OutputDelivery {
+    fn from(backlog: OutputBacklog) -> Self {
+        OutputDelivery::Backlog(backlog.writer())
+    }
+}
+
+/// # 输出接收端
+///
+/// 读取循环转发数据的目标。
+#[derive(Debug, Clone)]
+pub(crate) enum OutputSink {
+    /// 广播原始数据
+    Broadcast(Sender<Bytes>),
+    /// 广播带有来源标记的数据块
+    Tagged(Sender<OutputChunk>),
+    /// 通过有界通道投递原始数据
+    Channel(mpsc::Sender<Bytes>),
+    /// 写入输出积压缓冲区
+    Backlog(BacklogWriter),
+}
+
+impl From<OutputDelivery> for OutputSink {
+    fn from(delivery: OutputDelivery) -> Self {
+        match delivery {
+            OutputDelivery::Broadcast(sender) => OutputSink::Broadcast(sender),
+            OutputDelivery::Channel(sender) => OutputSink::Channel(sender),
+            OutputDelivery::Backlog(writer) => OutputSink::Backlog(writer),
+        }
+    }
+}
+
+impl OutputSink {
+    /// # 发送数据
+    ///
+    /// 广播方式只在有订阅者时发送；通道方式在通道写满时等待，直到消费者取走数据或关闭通道；
+    /// 积压缓冲区方式总是保留数据，并在有订阅者时广播。
+    async fn send(&self, stream: OutputStream, data: Bytes) {
+        let result = match self {
+            OutputSink::Broadcast(sender) => {
+                // 有订阅者才发送消息
+                let receiver_count = sender.receiver_count();
+                if receiver_count == 0 {
+                    return;
+                }
+                trace!("command process receiver count: {}", receiver_count);
+                sender.send(data).map(|_| ()).map_err(|e| e.to_string())
+            }
+            OutputSink::Tagged(sender) => {
+                let receiver_count = sender.receiver_count();
+                if receiver_count == 0 {
+                    return;
+                }
+                trace!("command process receiver count: {}", receiver_count);
+                sender
+                    .send(OutputChunk { stream, data })
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            }
+            OutputSink::Channel(sender) => {
+                if sender.is_closed() {
+                    return;
+                }
+                sender.send(data).await.map_err(|e| e.to_string())
+            }
+            OutputSink::Backlog(writer) => {
+                writer.send(data);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            warn!(
+                "Failed to send command process {stream} to receiver: {:#}",
+                e
+            );
+        }
+    }
+}
+
+/// # 读取子进程的输出
+///
+/// 异步读取子进程的输出管道，按分帧配置切分为完整的记录后转发给指定的接收端，
+/// 设置了输出日志时同时写入日志文件。标准输出和标准错误共用此读取循环。
+///
+/// ## 参数
+///
+/// * `reader` - 子进程的输出管道
+/// * `stream` - 管道对应的输出流，用于日志记录
+/// * `sink` - 用于转发输出数据的接收端
+/// * `read_buffer_size` - 读取缓冲区大小
+/// * `framing` - 输出分帧配置
+/// * `log` - 输出日志写入器
+///
+/// ## 返回值
+///
+/// 读取到 EOF 时返回 `Ok(())`，读取管道发生错误时返回该错误。
+pub(crate) async fn read_output<R: AsyncRead + Unpin>(
+    reader: R,
+    stream: OutputStream,
+    sink: OutputSink,
+    read_buffer_size: usize,
+    framing: OutputFraming,
+    log: Option<OutputLogWriter>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut buffer = vec![0u8; read_buffer_size];
+    let mut decoder = FrameDecoder::new(framing);
+    let result = loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => {
+                debug!("command process {stream} closed");
+                break Ok(());
+            }
+            Ok(n) => {
+                let frames = decoder.decode(&buffer[..n]);
+                if let Some(log) = &log {
+                    log.write(frames.clone()).await;
+                }
+                for frame in frames {
+                    sink.send(stream, frame).await;
+                }
+            }
+            Err(e) => {
+                error!("read command process {stream} error: {:#}", e);
+                break Err(e);
+            }
+        }
+    };
+    if let Some(frame) = decoder.finish() {
+        if let Some(log) = &log {
+            log.write(vec![frame.clone()]).await;
+        }
+        sink.send(stream, frame).await;
+    }
+    result
+}
@@ -0,0 +1,306 @@
+//! # 异步管道命令模块
+//!
+//! 提供 [SpawnPipeline] 结构体，在 tokio 运行时中启动由多个命令组成的管道，
+//! 将最后一个阶段的输出异步转发给订阅者，并通过 [PipelineHandle] 管理所有阶段。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::output_framing::OutputFraming;
+use crate::cmd::spawn::output_log::OutputLog;
+use crate::cmd::spawn::spawn_builder::SpawnBuilder;
+use crate::cmd::spawn::spawn_handle::{CmdExitStatus, SpawnHandle, StopStage};
+use crate::cmd::spawn::spawn_output::{OutputDelivery, OutputSink, StderrMode};
+use crate::cmd::std::CmdBuilder;
+use crate::process::parse_signal_instruction;
+use nix::sys::signal::Signal;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+/// # 管道命令退出状态
+///
+/// 管道中每个阶段的退出状态，顺序与管道中命令的顺序一致。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PipelineExitStatus {
+    /// 每个阶段的退出状态
+    pub stages: Vec<CmdExitStatus>,
+}
+
+impl PipelineExitStatus {
+    /// # 管道命令是否执行成功
+    ///
+    /// 与 shell 的 `pipefail` 语义一致，仅当所有阶段都执行成功时返回 `true`。
+    pub fn success(&self) -> bool {
+        self.stages.iter().all(CmdExitStatus::success)
+    }
+}
+
+/// # 异步管道命令
+///
+/// 依次连接多个命令的标准输出和标准输入并异步启动，最后一个阶段的输出由后台任务读取并投递给消费者。
+/// 每个阶段的参数、环境变量、工作目录和进程组使用各自 [CmdBuilder] 中的设置，
+/// 读取缓冲区大小、标准错误处理方式和输出分帧配置对所有阶段生效。
+///
+/// ## 示例
+///
+/// ```
+/// use tokio::sync::{mpsc, oneshot};
+/// use wheel_rs::cmd::spawn::SpawnPipeline;
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let (data_sender, mut data_receiver) = mpsc::channel(16);
+///     let (process_exit_sender, process_exit_receiver) = oneshot::channel();
+///     SpawnPipeline::new(CmdBuilder::new("seq").arg("3"))
+///         .pipe(CmdBuilder::new("tac"))
+///         .pipe(CmdBuilder::new("sh").args(["-c", "cat; exit 4"]))
+///         .spawn(data_sender, process_exit_sender)
+///         .unwrap();
+///     let mut output = Vec::new();
+///     while let Some(data) = data_receiver.recv().await {
+///         output.extend_from_slice(&data);
+///     }
+///     assert_eq!(output, b"3\n2\n1\n");
+///     let exit_status = process_exit_receiver.await.unwrap();
+///     assert!(!exit_status.success());
+///     let codes: Vec<_> = exit_status.stages.iter().map(|stage| stage.code).collect();
+///     assert_eq!(codes, [Some(0), Some(0), Some(4)]);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpawnPipeline {
+    /// 管道中的各个阶段
+    stages: Vec<CmdBuilder>,
+    /// 启动各个阶段使用的配置
+    builder: SpawnBuilder,
+}
+
+impl SpawnPipeline {
+    /// # 创建异步管道命令
+    ///
+    /// ## 参数
+    ///
+    /// * `first` - 管道的第一个阶段
+    pub fn new(first: CmdBuilder) -> Self {
+        Self {
+            builder: SpawnBuilder::new(first.clone()),
+            stages: vec![first],
+        }
+    }
+
+    /// # 在管道末尾添加一个阶段
+    ///
+    /// 上一个阶段的标准输出将连接到该阶段的标准输入。
+    pub fn pipe(mut self, stage: CmdBuilder) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// # 设置读取缓冲区大小
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.builder = self.builder.read_buffer_size(read_buffer_size);
+        self
+    }
+
+    /// # 设置标准错误处理方式
+    ///
+    /// 对所有阶段生效，默认丢弃标准错误输出。
+    pub fn stderr(mut self, stderr: StderrMode) -> Self {
+        self.builder = self.builder.stderr(stderr);
+        self
+    }
+
+    /// # 设置输出分帧配置
+    ///
+    /// 对最后一个阶段的标准输出和所有阶段的标准错误生效。
+    pub fn framing(mut self, framing: OutputFraming) -> Self {
+        self.builder = self.builder.framing(framing);
+        self
+    }
+
+    /// # 设置输出日志
+    ///
+    /// 最后一个阶段的标准输出和所有阶段未被丢弃的标准错误写入同一个日志文件。
+    pub fn output_log(mut self, output_log: OutputLog) -> Self {
+        self.builder = self.builder.output_log(output_log);
+        self
+    }
+
+    /// # 启用第一个阶段的标准输入写入器
+    ///
+    /// 通过第一个阶段的 [SpawnHandle::stdin] 向管道写入数据，默认第一个阶段继承父进程的标准输入。
+    pub fn stdin_writer(mut self) -> Self {
+        self.builder = self.builder.stdin_writer();
+        self
+    }
+
+    /// # 启动管道命令
+    ///
+    /// 启动所有阶段并返回管道句柄。所有阶段都被回收且最后一个阶段的标准输出读取结束后，
+    /// 每个阶段的退出状态会通过 `process_exit_sender` 发送。
+    ///
+    /// ## 参数
+    ///
+    /// * `output` - 最后一个阶段输出的投递方式，详见 [OutputDelivery]
+    /// * `process_exit_sender` - 用于发送管道退出状态的通道发送者
+    ///
+    /// ## 返回值
+    ///
+    /// 返回管道句柄，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// 任意一个阶段启动失败时返回相应的错误，已启动的阶段会被杀死。
+    pub fn spawn(
+        &self,
+        output: impl Into<OutputDelivery>,
+        process_exit_sender: oneshot::Sender<PipelineExitStatus>,
+    ) -> Result<PipelineHandle, CmdError> {
+        let sink = OutputSink::from(output.into());
+        let mut handles: Vec<SpawnHandle> = Vec::with_capacity(self.stages.len());
+        let mut stdin = Some(self.builder.stdin_stdio());
+        let log = self.builder.open_output_log()?;
+        for (index, stage) in self.stages.iter().enumerate() {
+            let forward_stdout = index + 1 == self.stages.len();
+            let builder = self.builder.clone().command(stage.clone());
+            let result = builder.spawn_stage(
+                stdin.take().unwrap_or_else(Stdio::null),
+                &sink,
+                forward_stdout,
+                log.clone(),
+                None,
+            );
+            let stdout = match result {
+                Ok((handle, stdout)) => {
+                    handles.push(handle);
+                    stdout
+                }
+                Err(e) => {
+                    kill_started_stages(&handles);
+                    return Err(e);
+                }
+            };
+            // 将标准输出交给下一个阶段，父进程不再持有
+            if let Some(stdout) = stdout {
+                match stdout.try_into() {
+                    Ok(stdout) => stdin = Some(stdout),
+                    Err(e) => {
+                        kill_started_stages(&handles);
+                        return Err(CmdError::Execute(e));
+                    }
+                }
+            }
+        }
+
+        let handle = PipelineHandle { stages: handles };
+        let waiter = handle.clone();
+        tokio::spawn(async move {
+            let exit_status = waiter.wait().await;
+            debug!("pipeline exited: {:?}", exit_status);
+            let _ = process_exit_sender.send(exit_status);
+        });
+        Ok(handle)
+    }
+}
+
+/// # 杀死已启动的阶段
+///
+/// 启动管道失败时调用，每个阶段的子进程由其后台任务负责回收。
+fn kill_started_stages(handles: &[SpawnHandle]) {
+    for handle in handles {
+        handle.start_signal(Signal::SIGKILL);
+    }
+}
+
+/// # 管道句柄
+///
+/// 管理管道中所有阶段的子进程句柄，可以被克隆。
+#[derive(Debug, Clone)]
+pub struct PipelineHandle {
+    /// 每个阶段的子进程句柄
+    stages: Vec<SpawnHandle>,
+}
+
+impl PipelineHandle {
+    /// # 获取每个阶段的子进程句柄
+    pub fn stages(&self) -> &[SpawnHandle] {
+        &self.stages
+    }
+
+    /// # 检查管道是否还在运行
+    ///
+    /// 任意一个阶段尚未被回收时返回 `true`。
+    pub fn is_alive(&self) -> bool {
+        self.stages.iter().any(SpawnHandle::is_alive)
+    }
+
+    /// # 等待所有阶段退出
+    ///
+    /// 等待所有阶段被回收且最后一个阶段的标准输出读取结束，返回每个阶段的退出状态。
+    /// 无法获取退出状态的阶段，其退出码和终止信号均为 `None`。
+    pub async fn wait(&self) -> PipelineExitStatus {
+        let mut stages = Vec::with_capacity(self.stages.len());
+        for stage in &self.stages {
+            let status = stage
+                .wait()
+                .await
+                .unwrap_or_else(|_| CmdExitStatus::new(None, false));
+            stages.push(status);
+        }
+        PipelineExitStatus { stages }
+    }
+
+    /// # 杀死所有阶段
+    ///
+    /// 向所有阶段发送 `SIGKILL` 信号，并等待其被回收。
+    pub async fn kill(&self) -> Result<(), CmdError> {
+        for stage in &self.stages {
+            stage.signal(Signal::SIGKILL).await?;
+        }
+        self.wait_reaped().await
+    }
+
+    /// # 优雅地停止所有阶段
+    ///
+    /// 先按指令向所有阶段发送信号，在宽限期内等待其全部退出；超过宽限期仍有阶段未退出时，
+    /// 升级为 `SIGKILL` 强制杀死所有阶段。
+    ///
+    /// ## 参数
+    ///
+    /// * `instruction` - 首先发送的信号指令，支持的指令见
+    ///   [send_signal_by_instruction](crate::process::send_signal_by_instruction)
+    /// * `grace_period` - 发送信号后等待所有阶段退出的宽限期
+    ///
+    /// ## 返回值
+    ///
+    /// 返回结束管道的阶段 [StopStage]，或者包含错误信息的 [CmdError]。
+    pub async fn stop(
+        &self,
+        instruction: &str,
+        grace_period: Duration,
+    ) -> Result<StopStage, CmdError> {
+        if !self.is_alive() {
+            return Ok(StopStage::AlreadyExited);
+        }
+        let signal = parse_signal_instruction(instruction)?;
+        for stage in &self.stages {
+            stage.signal(signal).await?;
+        }
+        if timeout(grace_period, self.wait_reaped()).await.is_ok() {
+            return Ok(StopStage::Graceful);
+        }
+        warn!("pipeline did not exit within {grace_period:?}, killing");
+        self.kill().await?;
+        Ok(StopStage::Killed)
+    }
+
+    /// # 等待所有阶段被回收
+    async fn wait_reaped(&self) -> Result<(), CmdError> {
+        for stage in &self.stages {
+            stage.wait_reaped().await?;
+        }
+        Ok(())
+    }
+}
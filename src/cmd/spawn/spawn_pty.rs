@@ -0,0 +1,186 @@
+//! # 伪终端模块
+//!
+//! 提供在伪终端（PTY）中运行子进程所需的类型。部分程序（如 `top`、交互式安装程序、彩色输出的命令行工具）
+//! 在标准输出不是终端时会改变行为，在伪终端中运行可以获得与真实终端一致的输出，
+//! 适用于在其上构建 Web 终端等场景。
+//!
+//! 伪终端由 [SpawnBuilder::spawn_pty](crate::cmd::spawn::SpawnBuilder::spawn_pty) 创建，
+//! 调用者通过 [PtyHandle] 向终端写入输入、调整窗口大小。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::spawn::spawn_handle::SpawnHandle;
+use nix::errno::Errno;
+use nix::pty::{Winsize, openpty};
+use nix::unistd::{read, write};
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, ReadBuf};
+use tracing::debug;
+
+/// # 伪终端窗口大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtySize {
+    /// 行数
+    pub rows: u16,
+    /// 列数
+    pub cols: u16,
+}
+
+impl Default for PtySize {
+    /// 默认窗口大小为 24 行 80 列
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+impl From<PtySize> for Winsize {
+    fn from(size: PtySize) -> Self {
+        Winsize {
+            ws_row: size.rows,
+            ws_col: size.cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+/// # 伪终端句柄
+///
+/// 持有伪终端的主设备，用于向子进程写入输入和调整窗口大小，并通过 [PtyHandle::handle]
+/// 获取子进程句柄。句柄可以被克隆，所有克隆共享同一个伪终端。
+#[derive(Debug, Clone)]
+pub struct PtyHandle {
+    /// 子进程句柄
+    handle: SpawnHandle,
+    /// 伪终端主设备
+    master: Arc<AsyncFd<OwnedFd>>,
+}
+
+impl PtyHandle {
+    /// # 创建伪终端句柄
+    pub(crate) fn new(handle: SpawnHandle, master: Arc<AsyncFd<OwnedFd>>) -> Self {
+        Self { handle, master }
+    }
+
+    /// # 获取子进程句柄
+    pub fn handle(&self) -> &SpawnHandle {
+        &self.handle
+    }
+
+    /// # 向终端写入输入
+    ///
+    /// 数据会被完整写入，相当于用户在终端中键入，如 `b"q"`、`b"\x03"`（Ctrl+C）。
+    ///
+    /// ## 错误处理
+    ///
+    /// 写入失败时返回 [CmdError::Pty] 错误。
+    pub async fn write(&self, data: &[u8]) -> Result<(), CmdError> {
+        let mut written = 0;
+        while written < data.len() {
+            let mut guard = self.master.writable().await.map_err(CmdError::Pty)?;
+            match guard
+                .try_io(|master| write(master.get_ref(), &data[written..]).map_err(io::Error::from))
+            {
+                Ok(result) => written += result.map_err(CmdError::Pty)?,
+                Err(_would_block) => continue,
+            }
+        }
+        Ok(())
+    }
+
+    /// # 调整终端窗口大小
+    ///
+    /// 子进程会收到 `SIGWINCH` 信号。
+    ///
+    /// ## 错误处理
+    ///
+    /// 设置失败时返回 [CmdError::Pty] 错误。
+    pub fn resize(&self, size: PtySize) -> Result<(), CmdError> {
+        debug!("resizing pty: {}x{}", size.cols, size.rows);
+        let winsize = Winsize::from(size);
+        // SAFETY: 主设备在句柄存活期间保持打开，winsize 在调用期间有效
+        let result = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &winsize) };
+        if result == -1 {
+            return Err(CmdError::Pty(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+/// # 伪终端主设备的读取端
+///
+/// 为主设备实现 [AsyncRead]，以便复用管道输出的读取循环。
+/// 所有从设备都关闭后读取主设备会返回 `EIO`，视为 EOF。
+pub(crate) struct PtyReader(pub(crate) Arc<AsyncFd<OwnedFd>>);
+
+impl AsyncRead for PtyReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            let unfilled = buf.initialize_unfilled();
+            match guard.try_io(|master| read(master.get_ref(), unfilled).map_err(io::Error::from)) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) if e.raw_os_error() == Some(Errno::EIO as i32) => {
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/// # 打开伪终端
+///
+/// 返回设置为非阻塞模式的主设备和从设备，两者都设置了 `FD_CLOEXEC`，不会泄漏给子进程执行的程序。
+///
+/// ## 错误处理
+///
+/// 打开伪终端或设置文件描述符标志失败时返回 [CmdError::Pty] 错误。
+pub(crate) fn open_pty(size: PtySize) -> Result<(AsyncFd<OwnedFd>, OwnedFd), CmdError> {
+    let pty = openpty(&Winsize::from(size), None).map_err(|e| CmdError::Pty(e.into()))?;
+    add_fd_flag(&pty.master, libc::F_GETFD, libc::F_SETFD, libc::FD_CLOEXEC)?;
+    add_fd_flag(&pty.slave, libc::F_GETFD, libc::F_SETFD, libc::FD_CLOEXEC)?;
+    add_fd_flag(&pty.master, libc::F_GETFL, libc::F_SETFL, libc::O_NONBLOCK)?;
+    let master = AsyncFd::new(pty.master).map_err(CmdError::Pty)?;
+    Ok((master, pty.slave))
+}
+
+/// # 为文件描述符添加标志
+fn add_fd_flag(
+    fd: &OwnedFd,
+    get: libc::c_int,
+    set: libc::c_int,
+    flag: libc::c_int,
+) -> Result<(), CmdError> {
+    // SAFETY: fd 是有效的文件描述符
+    unsafe {
+        let flags = libc::fcntl(fd.as_raw_fd(), get);
+        if flags == -1 || libc::fcntl(fd.as_raw_fd(), set, flags | flag) == -1 {
+            return Err(CmdError::Pty(io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+/// # 将标准输入设置为控制终端
+///
+/// 在子进程 `exec` 之前调用，此时子进程已是新会话的首进程，标准输入已重定向到伪终端从设备。
+pub(crate) fn set_controlling_terminal() -> io::Result<()> {
+    // SAFETY: ioctl 是异步信号安全的
+    if unsafe { libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY, 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+//! # 子进程标准输入模块
+//!
+//! 提供 [StdinWriter] 结构体，用于向子进程的标准输入异步写入数据，
+//! 以驱动 `sqlite3`、`redis-cli` 等交互式（REPL 风格）的子进程。
+
+use crate::cmd::cmd_error::CmdError;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::process::ChildStdin;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+/// # 标准输入写入器
+///
+/// 连接到子进程的标准输入管道。写入器可以被克隆，所有克隆共享同一个管道，
+/// 并发写入时每次写入的数据不会交错。调用 [StdinWriter::close] 后子进程将读到 EOF。
+#[derive(Debug, Clone)]
+pub struct StdinWriter {
+    /// 子进程的标准输入管道，关闭后为 `None`
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+}
+
+impl StdinWriter {
+    /// # 创建标准输入写入器
+    pub(crate) fn new(stdin: ChildStdin) -> Self {
+        Self {
+            stdin: Arc::new(Mutex::new(Some(stdin))),
+        }
+    }
+
+    /// # 写入数据
+    ///
+    /// 数据会被完整写入并刷新。子进程没有及时读取导致管道缓冲区写满时，会等待子进程读取。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 标准输入已关闭时，返回 [CmdError::StdinClosed] 错误。
+    /// * 写入失败（如子进程已退出）时，返回 [CmdError::WriteStdin] 错误。
+    pub async fn write(&self, data: impl AsRef<[u8]>) -> Result<(), CmdError> {
+        let mut stdin = self.stdin.lock().await;
+        let stdin = stdin.as_mut().ok_or(CmdError::StdinClosed)?;
+        stdin
+            .write_all(data.as_ref())
+            .await
+            .map_err(CmdError::WriteStdin)?;
+        stdin.flush().await.map_err(CmdError::WriteStdin)
+    }
+
+    /// # 关闭标准输入
+    ///
+    /// 关闭后子进程将读到 EOF，重复关闭不会报错。
+    pub async fn close(&self) {
+        if self.stdin.lock().await.take().is_some() {
+            debug!("command process stdin closed");
+        }
+    }
+
+    /// # 标准输入是否已关闭
+    pub async fn is_closed(&self) -> bool {
+        self.stdin.lock().await.is_none()
+    }
+}
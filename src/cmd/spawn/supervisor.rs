@@ -0,0 +1,618 @@
+//! # 进程守护模块
+//!
+//! 提供 [Supervisor] 结构体，持有命令的启动配置，在子进程退出后按重启策略以指数退避的方式重新启动，
+//! 并通过广播通道发布状态变化，避免每个服务都编写自己的重启循环。
+
+use crate::cmd::spawn::health_check::HealthCheck;
+use crate::cmd::spawn::spawn_builder::SpawnBuilder;
+use crate::cmd::spawn::spawn_handle::{CmdExitStatus, SpawnHandle};
+use crate::cmd::spawn::spawn_output::OutputDelivery;
+use bytes::Bytes;
+use nix::sys::signal::Signal;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, sleep};
+use tracing::{debug, error, info, warn};
+
+/// # 重启策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// 子进程退出后总是重启
+    Always,
+    /// 仅当子进程执行失败（退出码非 0、被信号终止或启动失败）时重启
+    #[default]
+    OnFailure,
+    /// 从不重启
+    Never,
+}
+
+impl RestartPolicy {
+    /// # 是否应该重启
+    ///
+    /// `exit_status` 为 `None` 表示子进程启动失败，`unhealthy` 表示子进程因健康检查不通过而被停止，
+    /// 此时无论其退出状态如何都按执行失败处理。
+    fn should_restart(&self, exit_status: Option<&CmdExitStatus>, unhealthy: bool) -> bool {
+        match self {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => {
+                unhealthy || exit_status.is_none_or(|status| !status.success())
+            }
+            RestartPolicy::Never => false,
+        }
+    }
+}
+
+/// # 守护事件
+///
+/// 守护任务的状态变化，通过 [Supervisor::subscribe] 或 [SupervisorHandle::subscribe] 订阅。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SupervisorEvent {
+    /// 子进程已启动，`restarts` 为此前已经重启的次数
+    Started {
+        /// 子进程 ID
+        pid: Option<u32>,
+        /// 已重启的次数
+        restarts: u32,
+    },
+    /// 子进程启动失败
+    SpawnFailed(String),
+    /// 子进程已退出并被回收
+    ///
+    /// 不等待标准输出读取结束，退出状态不包含读取标准输出的结果。
+    Exited(CmdExitStatus),
+    /// 等待指定的退避时间后重启
+    Backoff(Duration),
+    /// 健康检查第一次通过，或从不健康状态恢复
+    Healthy,
+    /// 健康检查连续失败次数达到阈值
+    Unhealthy {
+        /// 连续失败的次数
+        failures: u32,
+    },
+    /// 收到停止请求，正在停止子进程
+    Stopping,
+    /// 守护任务已结束，不再重启子进程
+    Stopped,
+    /// 在时间窗口内重启次数达到上限，放弃重启
+    GaveUp,
+}
+
+/// # 进程守护者
+///
+/// 持有命令的启动配置和重启策略，调用 [Supervisor::start] 后在后台任务中启动子进程，
+/// 子进程退出后按策略重启。每次重启前的等待时间从初始退避时间开始逐次翻倍，直到最大退避时间；
+/// 子进程持续运行超过重启窗口后，退避时间恢复为初始值。重启窗口内的重启次数达到上限时放弃重启。
+/// 设置 [HealthCheck] 后，子进程运行期间会定期检查其是否就绪。
+///
+/// 以下情况会优雅地停止子进程并结束守护任务：
+/// * 调用 [SupervisorHandle::stop] 或丢弃 [SupervisorHandle]
+/// * 通过 [Supervisor::signals] 设置的信号接收者收到 `SIGTERM` 信号
+///
+/// ## 示例
+///
+/// ```
+/// use std::time::Duration;
+/// use tokio::sync::broadcast;
+/// use wheel_rs::cmd::spawn::{RestartPolicy, SpawnBuilder, Supervisor, SupervisorEvent};
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// #[tokio::main(flavor = "current_thread")]
+/// async fn main() {
+///     let supervisor = Supervisor::new(SpawnBuilder::new(CmdBuilder::new("false")))
+///         .policy(RestartPolicy::OnFailure)
+///         .backoff(Duration::from_millis(10), Duration::from_millis(40))
+///         .max_restarts(2, Duration::from_secs(60));
+///     let mut events = supervisor.subscribe();
+///     let (data_sender, _) = broadcast::channel(16);
+///     let _handle = supervisor.start(data_sender);
+///
+///     let mut starts = 0;
+///     loop {
+///         match events.recv().await.unwrap() {
+///             SupervisorEvent::Started { .. } => starts += 1,
+///             SupervisorEvent::GaveUp => break,
+///             _ => {}
+///         }
+///     }
+///     // 首次启动加上 2 次重启
+///     assert_eq!(starts, 3);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Supervisor {
+    /// 子进程的启动配置
+    builder: SpawnBuilder,
+    /// 重启策略
+    policy: RestartPolicy,
+    /// 初始退避时间
+    initial_backoff: Duration,
+    /// 最大退避时间
+    max_backoff: Duration,
+    /// 重启窗口内允许的最大重启次数
+    max_restarts: usize,
+    /// 重启窗口
+    restart_window: Duration,
+    /// 停止子进程时首先发送的信号指令
+    stop_instruction: String,
+    /// 停止子进程的宽限期
+    grace_period: Duration,
+    /// 健康检查
+    health_check: Option<HealthCheck>,
+    /// 信号接收者，收到 `SIGTERM` 时停止守护
+    signals: Option<broadcast::Receiver<Signal>>,
+    /// 守护事件发送者
+    events: broadcast::Sender<SupervisorEvent>,
+}
+
+impl Supervisor {
+    /// # 创建进程守护者
+    ///
+    /// 默认仅在失败时重启，退避时间从 1 秒开始、最长 60 秒，60 秒内最多重启 5 次，
+    /// 停止时先发送 `SIGTERM`，10 秒后仍未退出则强制杀死。
+    ///
+    /// ## 参数
+    ///
+    /// * `builder` - 子进程的启动配置，每次启动都使用该配置
+    pub fn new(builder: SpawnBuilder) -> Self {
+        let (events, _) = broadcast::channel(64);
+        Self {
+            builder,
+            policy: RestartPolicy::default(),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            max_restarts: 5,
+            restart_window: Duration::from_secs(60),
+            stop_instruction: "terminate".to_string(),
+            grace_period: Duration::from_secs(10),
+            health_check: None,
+            signals: None,
+            events,
+        }
+    }
+
+    /// # 设置重启策略
+    pub fn policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// # 设置退避时间
+    ///
+    /// ## 参数
+    ///
+    /// * `initial` - 第一次重启前的等待时间
+    /// * `max` - 等待时间逐次翻倍的上限
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// # 设置重启次数上限
+    ///
+    /// ## 参数
+    ///
+    /// * `max_restarts` - 重启窗口内允许的最大重启次数
+    /// * `window` - 重启窗口
+    pub fn max_restarts(mut self, max_restarts: usize, window: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.restart_window = window;
+        self
+    }
+
+    /// # 设置停止方式
+    ///
+    /// ## 参数
+    ///
+    /// * `instruction` - 停止子进程时首先发送的信号指令，支持的指令见
+    ///   [send_signal_by_instruction](crate::process::send_signal_by_instruction)
+    /// * `grace_period` - 发送信号后等待子进程退出的宽限期，超过后强制杀死
+    pub fn stop_signal(mut self, instruction: &str, grace_period: Duration) -> Self {
+        self.stop_instruction = instruction.to_string();
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// # 设置健康检查
+    ///
+    /// 健康检查判定为不健康并重启子进程时，子进程的退出按执行失败处理，同样受重启策略和重启次数上限的约束。
+    pub fn health_check(mut self, health_check: HealthCheck) -> Self {
+        self.health_check = Some(health_check);
+        self
+    }
+
+    /// # 设置信号接收者
+    ///
+    /// 通常传入 [watch_signal](crate::process::watch_signal) 返回的接收者，
+    /// 收到 `SIGTERM` 信号时优雅地停止子进程并结束守护。
+    pub fn signals(mut self, signals: broadcast::Receiver<Signal>) -> Self {
+        self.signals = Some(signals);
+        self
+    }
+
+    /// # 订阅守护事件
+    ///
+    /// 在 [Supervisor::start] 之前订阅可以收到第一次启动的事件。
+    pub fn subscribe(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.events.subscribe()
+    }
+
+    /// # 启动守护
+    ///
+    /// 在后台任务中启动子进程并开始守护，必须在 tokio 运行时中调用。
+    ///
+    /// ## 参数
+    ///
+    /// * `output` - 子进程输出的投递方式，每次重启都投递到同一个目标，详见 [OutputDelivery]
+    ///
+    /// ## 返回值
+    ///
+    /// 返回守护句柄 [SupervisorHandle]。
+    pub fn start(self, output: impl Into<OutputDelivery>) -> SupervisorHandle {
+        let (stop_sender, stop_receiver) = watch::channel(false);
+        let events = self.events.clone();
+        let mut output = output.into();
+        let mut output_tap = None;
+        if self
+            .health_check
+            .as_ref()
+            .is_some_and(HealthCheck::watches_output)
+        {
+            let (delivery, tap) = tap_output(output);
+            output = delivery;
+            output_tap = Some(tap);
+        }
+        let task = tokio::spawn(self.run(output, output_tap, stop_receiver));
+        SupervisorHandle {
+            events,
+            stop_sender,
+            task: Some(task),
+        }
+    }
+
+    /// # 守护循环
+    async fn run(
+        mut self,
+        output: OutputDelivery,
+        output_tap: Option<broadcast::Sender<Bytes>>,
+        mut stop_receiver: watch::Receiver<bool>,
+    ) {
+        let mut restart_times: VecDeque<Instant> = VecDeque::new();
+        let mut backoff = self.initial_backoff;
+        let mut restarts = 0;
+        loop {
+            // 在启动前订阅，避免健康检查错过子进程最早的输出
+            let output_receiver = output_tap.as_ref().map(broadcast::Sender::subscribe);
+            // 按子进程被回收的时间重启，后代进程继承并持有标准输出时不会阻塞重启
+            let (process_exit_sender, _) = oneshot::channel();
+            let mut unhealthy = false;
+            let exit_status = match self.builder.spawn(output.clone(), process_exit_sender) {
+                Ok(handle) => {
+                    info!("supervised process started: {:?}", handle.id());
+                    self.emit(SupervisorEvent::Started {
+                        pid: handle.id(),
+                        restarts,
+                    });
+                    let started_at = Instant::now();
+                    tokio::select! {
+                        exit_status = handle.wait_reaped() => {
+                            let exit_status =
+                                exit_status.unwrap_or_else(|_| CmdExitStatus::new(None, false));
+                            if started_at.elapsed() >= self.restart_window {
+                                backoff = self.initial_backoff;
+                            }
+                            Some(exit_status)
+                        }
+                        _ = monitor_health(self.health_check.as_ref(), output_receiver, &self.events) => {
+                            warn!("supervised process unhealthy, restarting: {:?}", handle.id());
+                            unhealthy = true;
+                            Some(self.restart_process(&handle).await)
+                        }
+                        _ = stop_requested(&mut stop_receiver, &mut self.signals) => {
+                            self.stop_process(&handle).await;
+                            return;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("supervised process spawn failed: {e}");
+                    self.emit(SupervisorEvent::SpawnFailed(e.to_string()));
+                    None
+                }
+            };
+
+            if let Some(exit_status) = exit_status {
+                warn!("supervised process exited: {exit_status:?}");
+                self.emit(SupervisorEvent::Exited(exit_status));
+            }
+            if !self.policy.should_restart(exit_status.as_ref(), unhealthy) {
+                self.emit(SupervisorEvent::Stopped);
+                return;
+            }
+
+            let now = Instant::now();
+            while restart_times
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= self.restart_window)
+            {
+                restart_times.pop_front();
+            }
+            if restart_times.len() >= self.max_restarts {
+                error!(
+                    "supervised process restarted {} times within {:?}, giving up",
+                    restart_times.len(),
+                    self.restart_window
+                );
+                self.emit(SupervisorEvent::GaveUp);
+                return;
+            }
+            restart_times.push_back(now);
+
+            debug!("restarting supervised process in {backoff:?}");
+            self.emit(SupervisorEvent::Backoff(backoff));
+            tokio::select! {
+                _ = sleep(backoff) => {}
+                _ = stop_requested(&mut stop_receiver, &mut self.signals) => {
+                    self.emit(SupervisorEvent::Stopping);
+                    self.emit(SupervisorEvent::Stopped);
+                    return;
+                }
+            }
+            backoff = (backoff * 2).min(self.max_backoff);
+            restarts += 1;
+        }
+    }
+
+    /// # 停止子进程
+    async fn stop_process(&self, handle: &SpawnHandle) {
+        info!("stopping supervised process: {:?}", handle.id());
+        self.emit(SupervisorEvent::Stopping);
+        if let Err(e) = handle.stop(&self.stop_instruction, self.grace_period).await {
+            error!("stop supervised process error: {e}");
+        }
+        if let Ok(exit_status) = handle.wait_reaped().await {
+            self.emit(SupervisorEvent::Exited(exit_status));
+        }
+        self.emit(SupervisorEvent::Stopped);
+    }
+
+    /// # 停止不健康的子进程以便重启
+    async fn restart_process(&self, handle: &SpawnHandle) -> CmdExitStatus {
+        if let Err(e) = handle.stop(&self.stop_instruction, self.grace_period).await {
+            error!("stop unhealthy process error: {e}");
+        }
+        handle
+            .wait_reaped()
+            .await
+            .unwrap_or_else(|_| CmdExitStatus::new(None, false))
+    }
+
+    /// # 发布守护事件
+    fn emit(&self, event: SupervisorEvent) {
+        // 没有订阅者时忽略
+        let _ = self.events.send(event);
+    }
+}
+
+/// # 分流子进程的输出
+///
+/// 返回新的输出投递方式和用于健康检查订阅输出的广播发送者。广播和积压缓冲区投递方式直接订阅原发送者；
+/// 通道投递方式通过转发任务在投递给原消费者的同时广播一份，保留原有的背压语义。
+fn tap_output(output: OutputDelivery) -> (OutputDelivery, broadcast::Sender<Bytes>) {
+    match output {
+        OutputDelivery::Broadcast(sender) => (OutputDelivery::Broadcast(sender.clone()), sender),
+        OutputDelivery::Channel(sender) => {
+            let (tap, _) = broadcast::channel(64);
+            let (forward_sender, mut forward_receiver) = mpsc::channel::<Bytes>(16);
+            let forward_tap = tap.clone();
+            tokio::spawn(async move {
+                while let Some(data) = forward_receiver.recv().await {
+                    let _ = forward_tap.send(data.clone());
+                    // 原消费者关闭通道后继续读取，保证健康检查仍能收到输出
+                    let _ = sender.send(data).await;
+                }
+            });
+            (OutputDelivery::Channel(forward_sender), tap)
+        }
+        OutputDelivery::Backlog(writer) => {
+            let tap = writer
+                .backlog()
+                .sender()
+                .unwrap_or_else(|| broadcast::channel(1).0);
+            (OutputDelivery::Backlog(writer), tap)
+        }
+    }
+}
+
+/// # 监控子进程的健康状态
+///
+/// 未设置健康检查时永不返回。
+async fn monitor_health(
+    health_check: Option<&HealthCheck>,
+    output: Option<broadcast::Receiver<Bytes>>,
+    events: &broadcast::Sender<SupervisorEvent>,
+) {
+    match health_check {
+        Some(health_check) => health_check.monitor(output, events).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// # 等待停止请求
+///
+/// 收到停止请求、守护句柄被丢弃或收到 `SIGTERM` 信号时返回。
+async fn stop_requested(
+    stop_receiver: &mut watch::Receiver<bool>,
+    signals: &mut Option<broadcast::Receiver<Signal>>,
+) {
+    loop {
+        let signal = async {
+            match signals {
+                Some(signals) => signals.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            changed = stop_receiver.changed() => {
+                if changed.is_err() || *stop_receiver.borrow() {
+                    return;
+                }
+            }
+            signal = signal => match signal {
+                Ok(Signal::SIGTERM) => return,
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => *signals = None,
+            },
+        }
+    }
+}
+
+/// # 守护句柄
+///
+/// 用于订阅守护事件和停止守护。句柄被丢弃时，后台任务会优雅地停止子进程并结束守护。
+#[derive(Debug)]
+pub struct SupervisorHandle {
+    /// 守护事件发送者
+    events: broadcast::Sender<SupervisorEvent>,
+    /// 停止请求发送者
+    stop_sender: watch::Sender<bool>,
+    /// 守护任务
+    task: Option<JoinHandle<()>>,
+}
+
+impl SupervisorHandle {
+    /// # 订阅守护事件
+    pub fn subscribe(&self) -> broadcast::Receiver<SupervisorEvent> {
+        self.events.subscribe()
+    }
+
+    /// # 守护是否已结束
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// # 停止守护
+    ///
+    /// 优雅地停止子进程，并等待守护任务结束。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use tokio::sync::broadcast;
+    /// use wheel_rs::cmd::spawn::{SpawnBuilder, Supervisor, SupervisorEvent};
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// #[tokio::main(flavor = "current_thread")]
+    /// async fn main() {
+    ///     let supervisor = Supervisor::new(SpawnBuilder::new(CmdBuilder::new("sleep").arg("30")));
+    ///     let mut events = supervisor.subscribe();
+    ///     let (data_sender, _) = broadcast::channel(16);
+    ///     let handle = supervisor.start(data_sender);
+    ///     assert!(matches!(events.recv().await.unwrap(), SupervisorEvent::Started { .. }));
+    ///
+    ///     handle.stop().await;
+    ///     assert_eq!(events.recv().await.unwrap(), SupervisorEvent::Stopping);
+    ///     let SupervisorEvent::Exited(exit_status) = events.recv().await.unwrap() else {
+    ///         panic!("expected exited event");
+    ///     };
+    ///     assert_eq!(exit_status.signal, Some(15));
+    ///     assert_eq!(events.recv().await.unwrap(), SupervisorEvent::Stopped);
+    /// }
+    /// ```
+    pub async fn stop(mut self) {
+        let _ = self.stop_sender.send(true);
+        if let Some(task) = self.task.take()
+            && let Err(e) = task.await
+        {
+            error!("supervisor task error: {e}");
+        }
+    }
+}
+
+impl Drop for SupervisorHandle {
+    fn drop(&mut self) {
+        // 通知后台任务停止，不等待其结束
+        let _ = self.stop_sender.send(true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::spawn::health_check::HealthProbe;
+    use crate::cmd::std::CmdBuilder;
+    use tokio::time::timeout;
+
+    /// 等待守护事件的超时时间，避免回归时测试一直挂起
+    const TEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[tokio::test]
+    async fn test_restart_unhealthy_process_exiting_successfully() {
+        // 收到 SIGTERM 时以状态码 0 退出
+        let script = "trap 'exit 0' TERM; while true; do sleep 0.05; done";
+        let builder = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", script]));
+        let check = HealthCheck::new(HealthProbe::Command(Box::new(CmdBuilder::new("false"))))
+            .interval(Duration::from_millis(100))
+            .failure_threshold(1);
+        let supervisor = Supervisor::new(builder)
+            .policy(RestartPolicy::OnFailure)
+            .backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .stop_signal("terminate", Duration::from_secs(5))
+            .health_check(check);
+        let mut events = supervisor.subscribe();
+        let (data_sender, _) = broadcast::channel(16);
+        let handle = supervisor.start(data_sender);
+
+        timeout(TEST_TIMEOUT, async {
+            loop {
+                match events.recv().await.unwrap() {
+                    SupervisorEvent::Exited(exit_status) => {
+                        assert!(exit_status.success());
+                        break;
+                    }
+                    SupervisorEvent::Stopped => panic!("unhealthy process was not restarted"),
+                    _ => {}
+                }
+            }
+            assert!(matches!(
+                events.recv().await.unwrap(),
+                SupervisorEvent::Backoff(_)
+            ));
+            assert!(matches!(
+                events.recv().await.unwrap(),
+                SupervisorEvent::Started { restarts: 1, .. }
+            ));
+        })
+        .await
+        .expect("unhealthy process was not restarted in time");
+        handle.stop().await;
+    }
+
+    /// 测试子进程退出后立即重启，不等待继承了标准输出的后代进程退出
+    #[tokio::test]
+    async fn test_restart_when_descendant_holds_stdout() {
+        let builder = SpawnBuilder::new(CmdBuilder::new("sh").args(["-c", "sleep 3 & exit 1"]));
+        let supervisor = Supervisor::new(builder)
+            .policy(RestartPolicy::OnFailure)
+            .backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .max_restarts(1, Duration::from_secs(60));
+        let mut events = supervisor.subscribe();
+        let (data_sender, _) = broadcast::channel(16);
+        let handle = supervisor.start(data_sender);
+
+        timeout(Duration::from_secs(2), async {
+            loop {
+                match events.recv().await.unwrap() {
+                    SupervisorEvent::Started { restarts: 1, .. } => break,
+                    SupervisorEvent::Stopped => panic!("exited process was not restarted"),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .expect("exited process was not restarted before its descendant exited");
+        handle.stop().await;
+    }
+}
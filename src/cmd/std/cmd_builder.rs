@@ -0,0 +1,508 @@
+//! # 命令构建器模块
+//!
+//! 提供 [CmdBuilder] 结构体，用于以链式调用的方式配置并同步执行外部命令。
+//!
+//! 相比 [execute](crate::cmd::std::cmd_utils::execute)，构建器额外支持：
+//! - 添加、删除、清空环境变量
+//! - 设置工作目录
+//! - 向子进程的标准输入写入数据
+//! - 设置运行超时时间，超时后强制杀死子进程
+//! - 在新的进程组或会话中启动子进程
+//! - 设置子进程的资源限制、进程优先级和文件权限掩码
+//! - 以指定的用户和用户组运行子进程
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::cmd_output::CmdOutput;
+use crate::cmd::process_group::ProcessGroup;
+use crate::cmd::resource_limits::ResourceLimits;
+use crate::cmd::run_as::RunAs;
+use crate::cmd::std::cmd_utils::kill_process_group;
+use crate::process::IdOrName;
+use nix::sys::resource::Resource;
+use nix::sys::signal::Signal;
+use std::io::{ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{io, thread};
+use tracing::{debug, warn};
+
+/// 等待子进程退出时轮询进程状态的间隔
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// # 环境变量操作
+///
+/// 按调用顺序记录对子进程环境变量的修改，执行时依次应用到 [Command] 上。
+#[derive(Debug, Clone)]
+enum EnvOp {
+    /// 设置环境变量
+    Set(String, String),
+    /// 删除环境变量
+    Remove(String),
+    /// 清空所有环境变量（包括从父进程继承的）
+    Clear,
+}
+
+/// # 命令构建器
+///
+/// 以链式调用的方式配置外部命令的参数、环境变量、工作目录、标准输入和超时时间，
+/// 然后通过 [CmdBuilder::execute] 同步执行并获取结果。
+///
+/// ## 示例
+///
+/// ```
+/// use std::time::Duration;
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// let output = CmdBuilder::new("cat")
+///     .env("LANG", "C")
+///     .current_dir("/tmp")
+///     .stdin("Hello, world!")
+///     .timeout(Duration::from_secs(5))
+///     .execute()
+///     .unwrap();
+/// assert_eq!(output.stdout, b"Hello, world!");
+/// ```
+#[derive(Debug, Clone)]
+pub struct CmdBuilder {
+    /// 要执行的命令名称
+    cmd: String,
+    /// 命令参数
+    args: Vec<String>,
+    /// 环境变量操作，按调用顺序应用
+    env_ops: Vec<EnvOp>,
+    /// 工作目录
+    current_dir: Option<PathBuf>,
+    /// 写入子进程标准输入的数据
+    stdin: Option<Vec<u8>>,
+    /// 运行超时时间
+    timeout: Option<Duration>,
+    /// 子进程的进程组
+    process_group: ProcessGroup,
+    /// 子进程的资源限制
+    resource_limits: ResourceLimits,
+    /// 子进程的运行身份
+    run_as: RunAs,
+}
+
+impl CmdBuilder {
+    /// # 创建命令构建器
+    ///
+    /// ## 参数
+    ///
+    /// * `cmd` - 要执行的命令名称
+    pub fn new(cmd: impl Into<String>) -> Self {
+        Self {
+            cmd: cmd.into(),
+            args: Vec::new(),
+            env_ops: Vec::new(),
+            current_dir: None,
+            stdin: None,
+            timeout: None,
+            process_group: ProcessGroup::default(),
+            resource_limits: ResourceLimits::default(),
+            run_as: RunAs::default(),
+        }
+    }
+
+    /// # 添加一个命令参数
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// # 添加多个命令参数
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// # 设置环境变量
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_ops.push(EnvOp::Set(key.into(), value.into()));
+        self
+    }
+
+    /// # 设置多个环境变量
+    pub fn envs<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_ops.extend(
+            vars.into_iter()
+                .map(|(key, value)| EnvOp::Set(key.into(), value.into())),
+        );
+        self
+    }
+
+    /// # 删除环境变量
+    ///
+    /// 子进程将不会继承父进程中的该环境变量。
+    pub fn env_remove(mut self, key: impl Into<String>) -> Self {
+        self.env_ops.push(EnvOp::Remove(key.into()));
+        self
+    }
+
+    /// # 清空环境变量
+    ///
+    /// 清空此前设置的以及从父进程继承的所有环境变量，之后设置的环境变量仍然生效。
+    pub fn env_clear(mut self) -> Self {
+        self.env_ops.push(EnvOp::Clear);
+        self
+    }
+
+    /// # 设置工作目录
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// # 设置写入子进程标准输入的数据
+    ///
+    /// 数据写入完成后会关闭标准输入，子进程将读到 EOF。
+    /// 未设置时子进程的标准输入为空设备。
+    pub fn stdin(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// # 设置运行超时时间
+    ///
+    /// 子进程运行超过该时间后会被强制杀死，并返回 [CmdError::Timeout] 错误。
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// # 设置子进程的进程组
+    ///
+    /// 子进程成为新进程组的组长时，超时后会杀死整个进程组，而不仅仅是子进程本身。
+    /// 默认继承父进程的进程组。
+    pub fn process_group(mut self, process_group: ProcessGroup) -> Self {
+        self.process_group = process_group;
+        self
+    }
+
+    /// # 设置资源限制
+    ///
+    /// 子进程执行目标程序之前调用 `setrlimit` 设置指定资源的软限制和硬限制，
+    /// 同一资源多次设置时以最后一次为准，`u64::MAX` 表示不限制。
+    /// 非特权进程不能将硬限制提高到超过当前值，此时启动命令返回包含 `EPERM` 的 [CmdError::Execute] 错误；
+    /// 软限制大于硬限制时，启动命令前返回 [CmdError::InvalidResourceLimit] 错误。
+    ///
+    /// ## 示例
+    ///
+    /// ```
+    /// use nix::sys::resource::Resource;
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// let output = CmdBuilder::new("sh")
+    ///     .args(["-c", "ulimit -n; ulimit -t"])
+    ///     .rlimit(Resource::RLIMIT_NOFILE, 64, 64)
+    ///     .rlimit(Resource::RLIMIT_CPU, 10, 10)
+    ///     .nice(10)
+    ///     .umask(0o077)
+    ///     .execute()
+    ///     .unwrap();
+    /// assert_eq!(output.stdout_lossy(), "64\n10\n");
+    /// ```
+    pub fn rlimit(mut self, resource: Resource, soft: u64, hard: u64) -> Self {
+        self.resource_limits.rlimit(resource, soft, hard);
+        self
+    }
+
+    /// # 设置进程优先级
+    ///
+    /// 子进程执行目标程序之前将其优先级（nice 值）设置为指定值，有效范围为 -20 到 19，
+    /// 值越大优先级越低。非特权进程不能将优先级设置得比当前更高，此时启动命令返回包含 `EACCES` 的
+    /// [CmdError::Execute] 错误；超出有效范围时，启动命令前返回 [CmdError::InvalidNice] 错误。
+    pub fn nice(mut self, nice: i32) -> Self {
+        self.resource_limits.nice(nice);
+        self
+    }
+
+    /// # 设置文件权限掩码
+    ///
+    /// 子进程执行目标程序之前将其文件权限掩码设置为指定值，如 `0o077`。
+    /// 大于 `0o777` 时，启动命令前返回 [CmdError::InvalidUmask] 错误。
+    pub fn umask(mut self, umask: u32) -> Self {
+        self.resource_limits.umask(umask);
+        self
+    }
+
+    /// # 设置运行子进程的用户
+    ///
+    /// 可以是用户ID或用户名，用户名从本地的 passwd 数据库中解析。未设置用户组时使用用户的主用户组，
+    /// 未设置附加用户组时使用用户所属的全部用户组；按ID指定的用户没有对应的账户时，必须设置用户组。
+    /// 切换用户需要当前进程具有相应的权限（通常为 root）。
+    ///
+    /// ## 示例
+    ///
+    /// ```no_run
+    /// use wheel_rs::cmd::std::CmdBuilder;
+    ///
+    /// let output = CmdBuilder::new("id")
+    ///     .user("nobody")
+    ///     .group("nogroup")
+    ///     .groups::<_, u32>([])
+    ///     .execute()
+    ///     .unwrap();
+    /// println!("{}", output.stdout_lossy());
+    /// ```
+    pub fn user(mut self, user: impl Into<IdOrName>) -> Self {
+        self.run_as.user(user.into());
+        self
+    }
+
+    /// # 设置运行子进程的用户组
+    ///
+    /// 可以是用户组ID或用户组名，用户组名从本地的 group 数据库中解析。
+    pub fn group(mut self, group: impl Into<IdOrName>) -> Self {
+        self.run_as.group(group.into());
+        self
+    }
+
+    /// # 设置运行子进程的附加用户组
+    ///
+    /// 可以是用户组ID或用户组名，传入空集合表示清空附加用户组。
+    pub fn groups<I, G>(mut self, groups: I) -> Self
+    where
+        I: IntoIterator<Item = G>,
+        G: Into<IdOrName>,
+    {
+        self.run_as.groups(groups.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// # 获取子进程的进程组
+    pub(crate) fn get_process_group(&self) -> ProcessGroup {
+        self.process_group
+    }
+
+    /// # 获取写入子进程标准输入的数据
+    pub(crate) fn get_stdin(&self) -> Option<&[u8]> {
+        self.stdin.as_deref()
+    }
+
+    /// # 获取运行超时时间
+    pub(crate) fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// # 获取命令行字符串
+    ///
+    /// 将命令名称和参数以空格拼接，用于日志记录。
+    pub(crate) fn command_line(&self) -> String {
+        let mut command_line = self.cmd.clone();
+        for arg in &self.args {
+            command_line.push(' ');
+            command_line.push_str(arg);
+        }
+        command_line
+    }
+
+    /// # 构造标准库的命令实例
+    ///
+    /// 将构建器中的命令名称、参数、环境变量、工作目录、进程组、资源限制和运行身份应用到新的 [Command] 上，
+    /// 不包含标准输入输出的设置。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 资源限制、进程优先级或文件权限掩码无效时，返回相应的 [CmdError]。
+    /// * 解析用户或用户组失败时，返回 [CmdError::User] 错误。
+    pub(crate) fn build_command(&self) -> Result<Command, CmdError> {
+        self.resource_limits.validate()?;
+        let mut command = Command::new(&self.cmd);
+        command.args(&self.args);
+        for env_op in &self.env_ops {
+            match env_op {
+                EnvOp::Set(key, value) => command.env(key, value),
+                EnvOp::Remove(key) => command.env_remove(key),
+                EnvOp::Clear => command.env_clear(),
+            };
+        }
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+        self.process_group.apply(&mut command);
+        self.resource_limits.apply(&mut command);
+        // 切换用户会失去提高资源限制和优先级的权限，因此最后应用
+        self.run_as.apply(&mut command)?;
+        Ok(command)
+    }
+
+    /// # 执行命令
+    ///
+    /// 启动子进程，写入标准输入数据，并等待其执行完成。标准输出和标准错误会被完整收集。
+    ///
+    /// ## 返回值
+    ///
+    /// 返回命令的执行结果 [CmdOutput]，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 资源限制、进程优先级或文件权限掩码无效时，返回相应的 [CmdError]。
+    /// * 解析用户或用户组失败时，返回 [CmdError::User] 错误。
+    /// * 无法启动命令或读写管道失败时，返回 [CmdError::Execute] 错误。
+    /// * 命令返回非零退出码或被信号终止时，返回携带完整输出的 [CmdError::Run] 错误。
+    /// * 命令运行超时时，返回 [CmdError::Timeout] 错误。
+    pub fn execute(&self) -> Result<CmdOutput, CmdError> {
+        debug!("executing command: {}", self.command_line());
+        let start = Instant::now();
+        let mut child = self
+            .build_command()?
+            .stdin(if self.stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(CmdError::Execute)?;
+
+        // 在独立线程中写入标准输入并读取输出，避免管道缓冲区写满导致死锁
+        let stdin_handle = write_pipe(child.stdin.take(), self.stdin.clone());
+        let stdout_handle = child.stdout.take().map(read_pipe);
+        let stderr_handle = child.stderr.take().map(read_pipe);
+
+        let status = wait_children(
+            std::slice::from_mut(&mut child),
+            &[self.process_group],
+            self.timeout,
+        )?
+        .remove(0)
+        .0;
+
+        if let Some(handle) = stdin_handle {
+            join_pipe(handle)?;
+        }
+        let output = CmdOutput::new(
+            status,
+            stdout_handle.map(join_pipe).transpose()?.unwrap_or_default(),
+            stderr_handle.map(join_pipe).transpose()?.unwrap_or_default(),
+            start.elapsed(),
+        );
+        debug!("command executed: {} ({})", self.cmd, status);
+
+        if !output.success() {
+            return Err(CmdError::Run(output));
+        }
+
+        Ok(output)
+    }
+}
+
+/// # 在独立线程中向管道写入数据
+///
+/// 写入完成后关闭管道。管道或数据为空时不启动线程。
+pub(crate) fn write_pipe<W: Write + Send + 'static>(
+    pipe: Option<W>,
+    data: Option<Vec<u8>>,
+) -> Option<JoinHandle<io::Result<()>>> {
+    let (mut pipe, data) = pipe.zip(data)?;
+    Some(thread::spawn(move || {
+        match pipe.write_all(&data) {
+            // 子进程未读取全部输入就关闭了标准输入，不视为错误
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+            result => result,
+        }
+    }))
+}
+
+/// # 在独立线程中读取管道的全部内容
+pub(crate) fn read_pipe<R: Read + Send + 'static>(mut pipe: R) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+/// # 等待管道读写线程结束并获取结果
+pub(crate) fn join_pipe<T>(handle: JoinHandle<io::Result<T>>) -> Result<T, CmdError> {
+    handle
+        .join()
+        .map_err(|_| CmdError::Execute(io::Error::other("pipe thread panicked")))?
+        .map_err(CmdError::Execute)
+}
+
+/// # 等待子进程退出
+///
+/// 等待所有子进程退出，返回与子进程顺序一致的退出状态及其退出时刻。只有一个子进程且未设置超时时间时阻塞等待；
+/// 否则轮询子进程状态直到全部退出，以便记录每个子进程各自的退出时刻。若超过超时时间仍有子进程未退出，
+/// 则杀死所有未退出的子进程并返回 [CmdError::Timeout] 错误。子进程为进程组组长时，杀死整个进程组，
+/// 避免后代进程继续持有输出管道。
+///
+/// ## 参数
+///
+/// * `children` - 要等待的子进程
+/// * `process_groups` - 每个子进程的进程组
+/// * `timeout` - 超时时间
+pub(crate) fn wait_children(
+    children: &mut [Child],
+    process_groups: &[ProcessGroup],
+    timeout: Option<Duration>,
+) -> Result<Vec<(ExitStatus, Instant)>, CmdError> {
+    if let [child] = children
+        && timeout.is_none()
+    {
+        let status = child.wait().map_err(CmdError::Execute)?;
+        return Ok(vec![(status, Instant::now())]);
+    }
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut statuses = vec![None; children.len()];
+    loop {
+        for (child, status) in children.iter_mut().zip(statuses.iter_mut()) {
+            if status.is_none() {
+                *status = child
+                    .try_wait()
+                    .map_err(CmdError::Execute)?
+                    .map(|exit_status| (exit_status, Instant::now()));
+            }
+        }
+        if statuses.iter().all(Option::is_some) {
+            return Ok(statuses.into_iter().flatten().collect());
+        }
+        let now = Instant::now();
+        let Some((timeout, deadline)) = timeout.zip(deadline) else {
+            thread::sleep(WAIT_POLL_INTERVAL);
+            continue;
+        };
+        if now >= deadline {
+            for ((child, process_group), status) in
+                children.iter_mut().zip(process_groups).zip(&statuses)
+            {
+                if status.is_none() {
+                    warn!("command timed out after {:?}, killing process: {}", timeout, child.id());
+                    kill_child(child, *process_group)?;
+                }
+            }
+            return Err(CmdError::Timeout(timeout));
+        }
+        thread::sleep(WAIT_POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// # 杀死子进程并等待其退出
+///
+/// 子进程为进程组组长时，杀死整个进程组。
+fn kill_child(child: &mut Child, process_group: ProcessGroup) -> Result<(), CmdError> {
+    if process_group.is_leader() {
+        match kill_process_group(child.id(), Signal::SIGKILL) {
+            Ok(()) | Err(CmdError::NoSuchProcess(_)) => {}
+            Err(e) => return Err(e),
+        }
+    } else {
+        child.kill().map_err(CmdError::Kill)?;
+    }
+    child.wait().map_err(CmdError::Kill)?;
+    Ok(())
+}
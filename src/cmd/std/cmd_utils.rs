@@ -6,15 +6,23 @@
 //! - 执行外部命令并获取输出
 //! - 检查进程是否存活
 //! - 杀死进程
+//! - 向整个进程组发送信号
 
 use crate::cmd::cmd_error::CmdError;
+use crate::cmd::std::cmd_builder::CmdBuilder;
+use libc::pid_t;
+use nix::errno::Errno;
+use nix::sys::signal::{Signal, kill, killpg};
+use nix::unistd::Pid;
+use std::io;
+use std::process::Child;
 use tracing::debug;
-use std::process::{Child, Command};
 
 /// # 执行外部命令
 ///
 /// 执行指定的外部命令并返回其标准输出。此函数会等待命令执行完成，
-/// 并检查命令执行结果状态。
+/// 并检查命令执行结果状态。需要设置环境变量、工作目录、标准输入或超时时间时，
+/// 请使用 [CmdBuilder]。
 ///
 /// ## 参数
 ///
@@ -28,6 +36,8 @@ use std::process::{Child, Command};
 /// ## 错误处理
 ///
 /// 如果命令执行失败或返回非零退出码，则返回相应的 [CmdError]。
+/// 其中 [CmdError::Run] 携带了完整的 [CmdOutput](crate::cmd::cmd_output::CmdOutput)，
+/// 可用于区分具体的退出码。
 ///
 /// ## 示例
 ///
@@ -42,20 +52,17 @@ use std::process::{Child, Command};
 ///     }
 ///     Err(e) => eprintln!("Command failed: {:#}", e),
 /// }
+///
+/// // 根据退出码区分失败原因
+/// use wheel_rs::cmd::cmd_error::CmdError;
+///
+/// match execute("sh", &["-c", "exit 2"]) {
+///     Err(CmdError::Run(output)) => assert_eq!(output.code, Some(2)),
+///     _ => unreachable!(),
+/// }
 /// ```
 pub fn execute(cmd: &str, args: &[&str]) -> Result<Vec<u8>, CmdError> {
-    debug!("executing command: {} {}", cmd, args.join(" "));
-    let output = Command::new(cmd)
-        .args(args)
-        .output()
-        .map_err(|e| CmdError::Execute(e))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        return Err(CmdError::Run(stderr));
-    }
-
-    Ok(output.stdout)
+    Ok(CmdBuilder::new(cmd).args(args.iter().copied()).execute()?.stdout)
 }
 
 /// # 检查进程是否还活着
@@ -101,44 +108,130 @@ pub fn kill_process(mut child: Child) -> Result<(), Box<dyn std::error::Error>>
     Ok(())
 }
 
-/// # 根据进程ID杀死进程
+/// # 根据进程ID向进程发送信号
 ///
-/// 根据指定的进程ID强制终止相应进程。
+/// 通过 `kill(2)` 系统调用向指定进程发送信号，通常用于强制终止进程（`SIGKILL`）。
 ///
 /// ## 参数
 ///
-/// * `process` - 要杀死的进程ID
+/// * `pid` - 目标进程ID
+/// * `signal` - 要发送的信号，如 [Signal::SIGKILL]、[Signal::SIGTERM]
 ///
 /// ## 返回值
 ///
-/// 如果成功杀死进程则返回 `Ok(())`，否则返回包含错误信息的 Result。
+/// 如果成功发送信号则返回 `Ok(())`，否则返回包含错误信息的 [CmdError]。
+///
+/// ## 错误处理
+///
+/// * 如果进程ID为 0 或超过 `i32::MAX`，则返回 [CmdError::InvalidPid] 错误。
+/// * 如果进程不存在，则返回 [CmdError::NoSuchProcess] 错误。
+/// * 如果没有权限向该进程发送信号，则返回 [CmdError::PermissionDenied] 错误。
+/// * 其他系统错误返回 [CmdError::Kill] 错误。
+///
+/// ## 示例
+///
+/// ```
+/// use nix::sys::signal::Signal;
+/// use wheel_rs::cmd::cmd_error::CmdError;
+/// use wheel_rs::cmd::std::cmd_utils::kill_process_by_id;
+///
+/// let child = std::process::Command::new("sleep").arg("10").spawn().unwrap();
+/// kill_process_by_id(child.id(), Signal::SIGKILL).unwrap();
+///
+/// assert!(matches!(
+///     kill_process_by_id(i32::MAX as u32, Signal::SIGKILL),
+///     Err(CmdError::NoSuchProcess(_))
+/// ));
+/// ```
+pub fn kill_process_by_id(pid: u32, signal: Signal) -> Result<(), CmdError> {
+    debug!("sending {} to process by id: {}", signal, pid);
+    kill(to_pid(pid)?, signal).map_err(|errno| kill_error(pid, errno))
+}
+
+/// # 根据进程组ID向整个进程组发送信号
 ///
-/// ## 平台差异
+/// 通过 `killpg(3)` 向进程组中的所有进程发送信号。以
+/// [ProcessGroup::NewGroup](crate::cmd::process_group::ProcessGroup::NewGroup) 或
+/// [ProcessGroup::NewSession](crate::cmd::process_group::ProcessGroup::NewSession) 启动的子进程是新进程组的组长，
+/// 其进程ID即为进程组ID，向其进程组发送信号可以一并结束它派生的所有后代进程。
 ///
-/// * Unix系统: 使用 `kill -9 <process>` 命令
-/// * Windows系统: 使用 `taskkill /F /PID <process>` 命令
+/// ## 参数
+///
+/// * `pgid` - 目标进程组ID
+/// * `signal` - 要发送的信号
+///
+/// ## 返回值
+///
+/// 如果成功发送信号则返回 `Ok(())`，否则返回包含错误信息的 [CmdError]。
 ///
 /// ## 错误处理
 ///
-/// 如果杀死进程过程中发生错误，则返回相应的错误信息。
-pub fn kill_process_by_id(pid: u32) -> std::io::Result<()> {
-    debug!("killing process by id: {}", pid);
-    #[cfg(unix)]
-    {
-        Command::new("kill")
-            .arg("-9")
-            .arg(&pid.to_string())
-            .output()?;
+/// * 如果进程组ID为 0 或超过 `i32::MAX`，则返回 [CmdError::InvalidPid] 错误。
+/// * 如果进程组不存在，则返回 [CmdError::NoSuchProcess] 错误。
+/// * 如果没有权限向该进程组发送信号，则返回 [CmdError::PermissionDenied] 错误。
+/// * 其他系统错误返回 [CmdError::Kill] 错误。
+///
+/// ## 示例
+///
+/// ```
+/// use nix::sys::signal::Signal;
+/// use std::time::Duration;
+/// use wheel_rs::cmd::cmd_error::CmdError;
+/// use wheel_rs::cmd::process_group::ProcessGroup;
+/// use wheel_rs::cmd::std::CmdBuilder;
+///
+/// // 超时后整个管道都会被杀死，不会因为 sleep 继续持有输出管道而阻塞
+/// let result = CmdBuilder::new("sh")
+///     .args(["-c", "sleep 10 | sleep 10"])
+///     .process_group(ProcessGroup::NewGroup)
+///     .timeout(Duration::from_millis(100))
+///     .execute();
+/// assert!(matches!(result, Err(CmdError::Timeout(_))));
+/// ```
+pub fn kill_process_group(pgid: u32, signal: Signal) -> Result<(), CmdError> {
+    debug!("sending {} to process group: {}", signal, pgid);
+    killpg(to_pid(pgid)?, signal).map_err(|errno| kill_error(pgid, errno))
+}
+
+/// # 将进程ID转换为 [Pid]
+///
+/// `kill(2)` 中 0 表示调用者所在的进程组，负数表示进程组或所有进程，超过 `i32::MAX` 的值转换后会变为负数，
+/// 因此只接受 1 到 `i32::MAX` 之间的进程ID，否则返回 [CmdError::InvalidPid] 错误。
+fn to_pid(pid: u32) -> Result<Pid, CmdError> {
+    match pid_t::try_from(pid) {
+        Ok(raw) if raw > 0 => Ok(Pid::from_raw(raw)),
+        _ => Err(CmdError::InvalidPid(pid)),
     }
+}
 
-    #[cfg(windows)]
-    {
-        Command::new("taskkill")
-            .arg("/F")
-            .arg("/PID")
-            .arg(&pid.to_string())
-            .output()?;
+/// # 将发送信号的系统错误转换为命令错误
+fn kill_error(pid: u32, errno: Errno) -> CmdError {
+    match errno {
+        Errno::ESRCH => CmdError::NoSuchProcess(pid),
+        Errno::EPERM => CmdError::PermissionDenied(pid),
+        errno => CmdError::Kill(io::Error::from(errno)),
     }
+}
 
-    Ok(())
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reject_invalid_pid() {
+        for pid in [0, i32::MAX as u32 + 1, u32::MAX] {
+            assert!(matches!(
+                kill_process_by_id(pid, Signal::SIGKILL),
+                Err(CmdError::InvalidPid(p)) if p == pid
+            ));
+            assert!(matches!(
+                kill_process_group(pid, Signal::SIGKILL),
+                Err(CmdError::InvalidPid(p)) if p == pid
+            ));
+        }
+        assert!(matches!(
+            kill_process_by_id(i32::MAX as u32, Signal::SIGKILL),
+            Err(CmdError::NoSuchProcess(_))
+        ));
+    }
+}
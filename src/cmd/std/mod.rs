@@ -1,4 +1,8 @@
+pub mod cmd_builder;
 pub mod cmd_utils;
+pub mod pipeline;
 
 // 重新导出结构体，简化外部引用
+pub use cmd_builder::*;
 pub use cmd_utils::*;
+pub use pipeline::*;
@@ -0,0 +1,196 @@
+//! # 管道命令模块
+//!
+//! 提供 [Pipeline] 结构体，将多个命令的标准输出依次连接到下一个命令的标准输入，
+//! 并同步执行，效果类似于 shell 中的 `a | b | c`，但不经过 shell，不存在参数注入的问题。
+
+use crate::cmd::cmd_error::CmdError;
+use crate::cmd::cmd_output::{CmdOutput, PipelineOutput};
+use crate::cmd::process_group::ProcessGroup;
+use crate::cmd::std::cmd_builder::{CmdBuilder, join_pipe, read_pipe, wait_children, write_pipe};
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// # 管道命令
+///
+/// 依次连接多个命令的标准输出和标准输入，同步执行并收集每个阶段的结果。
+/// 每个阶段的参数、环境变量、工作目录和进程组使用各自 [CmdBuilder] 中的设置，
+/// 各阶段自身的标准输入和超时设置不会被使用。
+///
+/// ## 示例
+///
+/// ```
+/// use wheel_rs::cmd::cmd_error::CmdError;
+/// use wheel_rs::cmd::std::{CmdBuilder, Pipeline};
+///
+/// let output = Pipeline::new(CmdBuilder::new("printf").arg("b\na\nb\n"))
+///     .pipe(CmdBuilder::new("sort"))
+///     .pipe(CmdBuilder::new("uniq"))
+///     .execute()
+///     .unwrap();
+/// assert_eq!(output.stdout(), b"a\nb\n");
+///
+/// // 任意一个阶段失败都会返回错误，并携带每个阶段的退出状态
+/// let result = Pipeline::new(CmdBuilder::new("false"))
+///     .pipe(CmdBuilder::new("cat"))
+///     .execute();
+/// let Err(CmdError::Pipeline(output)) = result else {
+///     panic!("pipeline should fail");
+/// };
+/// assert_eq!(output.stages[0].code, Some(1));
+/// assert_eq!(output.stages[1].code, Some(0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    /// 管道中的各个阶段
+    stages: Vec<CmdBuilder>,
+    /// 写入第一个阶段标准输入的数据
+    stdin: Option<Vec<u8>>,
+    /// 整个管道的运行超时时间
+    timeout: Option<Duration>,
+}
+
+impl Pipeline {
+    /// # 创建管道命令
+    ///
+    /// ## 参数
+    ///
+    /// * `first` - 管道的第一个阶段
+    pub fn new(first: CmdBuilder) -> Self {
+        Self {
+            stages: vec![first],
+            stdin: None,
+            timeout: None,
+        }
+    }
+
+    /// # 在管道末尾添加一个阶段
+    ///
+    /// 上一个阶段的标准输出将连接到该阶段的标准输入。
+    pub fn pipe(mut self, stage: CmdBuilder) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// # 设置写入第一个阶段标准输入的数据
+    ///
+    /// 未设置时第一个阶段的标准输入为空设备。
+    pub fn stdin(mut self, data: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(data.into());
+        self
+    }
+
+    /// # 设置整个管道的运行超时时间
+    ///
+    /// 超时后所有尚未退出的阶段都会被强制杀死，并返回 [CmdError::Timeout] 错误。
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// # 获取命令行字符串
+    fn command_line(&self) -> String {
+        self.stages
+            .iter()
+            .map(CmdBuilder::command_line)
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// # 执行管道命令
+    ///
+    /// 启动所有阶段并等待其全部退出。最后一个阶段的标准输出和每个阶段的标准错误会被完整收集，
+    /// 每个阶段的运行时长为从启动管道到该阶段退出的时间。
+    ///
+    /// ## 返回值
+    ///
+    /// 返回每个阶段的执行结果 [PipelineOutput]，或者包含错误信息的 [CmdError]。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 任意一个阶段的资源限制、进程优先级或文件权限掩码无效时，返回相应的 [CmdError]。
+    /// * 无法启动任意一个阶段或读写管道失败时，返回 [CmdError::Execute] 错误，已启动的阶段会被杀死。
+    /// * 任意一个阶段返回非零退出码或被信号终止时，返回携带每个阶段输出的 [CmdError::Pipeline] 错误。
+    /// * 管道运行超时时，返回 [CmdError::Timeout] 错误。
+    pub fn execute(&self) -> Result<PipelineOutput, CmdError> {
+        debug!("executing pipeline: {}", self.command_line());
+        let start = Instant::now();
+        let commands = self
+            .stages
+            .iter()
+            .map(CmdBuilder::build_command)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut children: Vec<Child> = Vec::with_capacity(commands.len());
+        for mut command in commands {
+            let stdin = match children.last_mut() {
+                // 将上一个阶段的标准输出交给当前阶段，父进程不再持有
+                Some(previous) => previous
+                    .stdout
+                    .take()
+                    .map(Stdio::from)
+                    .unwrap_or_else(Stdio::null),
+                None if self.stdin.is_some() => Stdio::piped(),
+                None => Stdio::null(),
+            };
+            let child = command
+                .stdin(stdin)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn();
+            let child = match child {
+                Ok(child) => child,
+                Err(e) => {
+                    for child in &mut children {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                    }
+                    return Err(CmdError::Execute(e));
+                }
+            };
+            children.push(child);
+        }
+
+        // 在独立线程中写入标准输入并读取输出，避免管道缓冲区写满导致死锁
+        let stdin_handle = write_pipe(children[0].stdin.take(), self.stdin.clone());
+        let stdout_handle = children
+            .last_mut()
+            .and_then(|child| child.stdout.take())
+            .map(read_pipe);
+        let stderr_handles: Vec<_> = children
+            .iter_mut()
+            .map(|child| child.stderr.take().map(read_pipe))
+            .collect();
+
+        let process_groups: Vec<ProcessGroup> = self
+            .stages
+            .iter()
+            .map(CmdBuilder::get_process_group)
+            .collect();
+        let statuses = wait_children(&mut children, &process_groups, self.timeout)?;
+
+        if let Some(handle) = stdin_handle {
+            join_pipe(handle)?;
+        }
+        let mut stages = Vec::with_capacity(statuses.len());
+        for ((status, exited_at), stderr_handle) in statuses.into_iter().zip(stderr_handles) {
+            let stderr = stderr_handle
+                .map(join_pipe)
+                .transpose()?
+                .unwrap_or_default();
+            // 所有阶段同时启动，各阶段的运行时间为从启动管道到该阶段退出
+            let duration = exited_at.duration_since(start);
+            stages.push(CmdOutput::new(status, Vec::new(), stderr, duration));
+        }
+        if let (Some(last), Some(handle)) = (stages.last_mut(), stdout_handle) {
+            last.stdout = join_pipe(handle)?;
+        }
+        let output = PipelineOutput { stages };
+        debug!("pipeline executed: {}", output);
+
+        if !output.success() {
+            return Err(CmdError::Pipeline(output));
+        }
+
+        Ok(output)
+    }
+}
@@ -0,0 +1,76 @@
+//! # 守护进程错误类型定义
+//!
+//! 定义守护进程化过程中可能出现的各种错误类型，系统调用失败的错误会保留底层的 [io::Error]。
+//! 该模块通过 `thiserror` 提供结构化的错误类型，方便上层业务逻辑进行模式匹配和错误传播。
+
+use crate::process::PidError;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// # 守护进程相关错误枚举
+///
+/// 包含守护进程化过程中可能发生的各种错误类型。在原进程中发生的错误直接返回，
+/// 在守护进程中发生的错误会通过管道报告给原进程，以 [DaemonError::Startup] 返回。
+#[derive(Error, Debug)]
+pub enum DaemonError {
+    /// 文件权限掩码无效错误
+    ///
+    /// 当文件权限掩码大于 `0o777` 时返回此错误
+    #[error("Invalid umask: {0:o}")]
+    InvalidUmask(u32),
+
+    /// 多线程进程错误
+    ///
+    /// 当调用者进程已经创建了多个线程时返回此错误，包含当前的线程数
+    #[error("Fail to daemonize multi-threaded process with {0} threads")]
+    MultiThreaded(usize),
+
+    /// 打开标准输入输出文件失败错误
+    ///
+    /// 当无法打开 `/dev/null` 或重定向标准输出、标准错误的文件时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to open stdio file {path}", path = .0.display())]
+    OpenStdio(PathBuf, #[source] io::Error),
+
+    /// 创建子进程失败错误
+    ///
+    /// 当 `fork` 或创建报告启动结果的管道失败时返回此错误
+    #[error("Fail to fork daemon process")]
+    Fork(#[source] io::Error),
+
+    /// 回收子进程失败错误
+    ///
+    /// 当原进程 `waitpid` 第一个子进程失败时返回此错误
+    #[error("Fail to wait for first child process")]
+    Wait(#[source] io::Error),
+
+    /// 创建新会话失败错误
+    ///
+    /// 当 `setsid` 失败时返回此错误
+    #[error("Fail to create session for daemon process")]
+    Setsid(#[source] io::Error),
+
+    /// 切换工作目录失败错误
+    ///
+    /// 当守护进程无法切换到指定的工作目录时返回此错误，包含目录路径和底层的 I/O 错误
+    #[error("Fail to change working directory to {path}", path = .0.display())]
+    ChangeDirectory(PathBuf, #[source] io::Error),
+
+    /// 重定向标准输入输出失败错误
+    ///
+    /// 当 `dup2` 失败时返回此错误
+    #[error("Fail to redirect stdio of daemon process")]
+    RedirectStdio(#[source] io::Error),
+
+    /// PID 文件错误
+    ///
+    /// 当获取或更新 PID 文件失败时返回此错误，如已有实例在运行时为 [PidError::AlreadyRunning]
+    #[error(transparent)]
+    Pid(#[from] PidError),
+
+    /// 守护进程启动失败错误
+    ///
+    /// 当守护进程在启动过程中失败时，原进程返回此错误，包含守护进程报告的错误信息
+    #[error("Daemon process failed to start: {0}")]
+    Startup(String),
+}
@@ -0,0 +1,385 @@
+//! # 守护进程化
+//!
+//! 提供 [Daemonize] 构建器，按经典的两次 `fork` 流程将当前进程转换为守护进程：
+//!
+//! 1. 原进程 `fork` 出第一个子进程，等待守护进程报告启动结果后退出；
+//! 2. 第一个子进程调用 `setsid` 创建新会话、脱离控制终端，再 `fork` 出守护进程后立即退出，
+//!    守护进程不是会话首进程，之后打开终端设备也不会重新获得控制终端；
+//! 3. 守护进程切换工作目录、设置文件权限掩码、重定向标准输入输出，并在 PID 文件中记录自己的 PID。
+
+use crate::process::pid::pid_utils::DEFAULT_PID_FILE_MODE;
+use crate::process::{DaemonError, PidError, PidFileGuard};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, PipeReader, PipeWriter, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use tracing::debug;
+
+/// 空设备的路径
+const DEV_NULL: &str = "/dev/null";
+/// 当前进程状态文件的路径
+const PROC_SELF_STATUS: &str = "/proc/self/status";
+/// 文件权限掩码的最大值
+const MAX_UMASK: u32 = 0o777;
+/// 守护进程启动成功时通过管道发送给原进程的标记
+const READY: u8 = 0;
+
+/// # 守护进程构建器
+///
+/// 默认不切换工作目录、不修改文件权限掩码、不记录 PID 文件，标准输入输出都重定向到 `/dev/null`。
+///
+/// 指定了 PID 文件时，原进程在 `fork` 之前获取 [PidFileGuard]，已有实例在运行等错误直接返回给调用者；
+/// 文件的排他锁随文件描述符被守护进程继承，最终由守护进程记录自己的 PID。
+///
+/// ## 注意事项
+///
+/// - `fork` 只会复制调用它的线程，必须在创建任何线程（包括 `tokio` 运行时）之前调用 [Daemonize::start]，
+///   否则返回 [DaemonError::MultiThreaded] 错误。
+/// - 原进程在守护进程启动成功后以状态码 0 退出，不会执行析构函数。
+///
+/// ## 示例
+///
+/// ```no_run
+/// use wheel_rs::process::Daemonize;
+///
+/// fn main() {
+///     let _pid_file = Daemonize::new()
+///         .working_directory("/")
+///         .umask(0o027)
+///         .stdout("/var/log/my_app/stdout.log")
+///         .stderr("/var/log/my_app/stderr.log")
+///         .pid_file("/run/my_app.pid")
+///         .start()
+///         .unwrap();
+///
+///     // 此后的代码在守护进程中执行，守卫存活期间持有 PID 文件，可以在这里创建 tokio 运行时
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Daemonize {
+    /// 守护进程的工作目录
+    working_directory: Option<PathBuf>,
+    /// 守护进程的文件权限掩码
+    umask: Option<u32>,
+    /// 标准输出重定向的文件，为 `None` 时重定向到 `/dev/null`
+    stdout: Option<PathBuf>,
+    /// 标准错误重定向的文件，为 `None` 时重定向到 `/dev/null`
+    stderr: Option<PathBuf>,
+    /// PID 文件的路径
+    pid_file: Option<PathBuf>,
+    /// PID 文件的权限，为 `None` 时使用默认权限 `0644`
+    pid_file_mode: Option<u32>,
+}
+
+impl Daemonize {
+    /// # 创建守护进程构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # 设置守护进程的工作目录
+    ///
+    /// 通常设置为 `/`，避免守护进程占用启动时所在的目录，导致其所在的文件系统无法卸载。
+    pub fn working_directory(mut self, working_directory: impl Into<PathBuf>) -> Self {
+        self.working_directory = Some(working_directory.into());
+        self
+    }
+
+    /// # 设置守护进程的文件权限掩码
+    ///
+    /// 掩码同样作用于新建的标准输出和标准错误文件。
+    /// 掩码大于 `0o777` 时，[Daemonize::start] 返回 [DaemonError::InvalidUmask] 错误。
+    pub fn umask(mut self, umask: u32) -> Self {
+        self.umask = Some(umask);
+        self
+    }
+
+    /// # 设置标准输出重定向的文件
+    ///
+    /// 文件不存在时创建，已存在时追加写入。
+    pub fn stdout(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stdout = Some(path.into());
+        self
+    }
+
+    /// # 设置标准错误重定向的文件
+    ///
+    /// 文件不存在时创建，已存在时追加写入。
+    pub fn stderr(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stderr = Some(path.into());
+        self
+    }
+
+    /// # 设置 PID 文件的路径
+    ///
+    /// 相对路径基于原进程的工作目录解析，不受守护进程切换工作目录的影响。
+    pub fn pid_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.pid_file = Some(path.into());
+        self
+    }
+
+    /// # 设置 PID 文件的权限
+    pub fn pid_file_mode(mut self, mode: u32) -> Self {
+        self.pid_file_mode = Some(mode);
+        self
+    }
+
+    /// # 将当前进程转换为守护进程
+    ///
+    /// 只在守护进程中返回。原进程等待守护进程报告启动结果，成功时以状态码 0 退出，失败时返回错误。
+    ///
+    /// ## 返回值
+    ///
+    /// * `Ok(Some(PidFileGuard))` - 守护进程启动成功，守卫需要在守护进程运行期间一直持有。
+    /// * `Ok(None)` - 守护进程启动成功，未指定 PID 文件。
+    /// * `Err(DaemonError)` - 在原进程中返回的错误。
+    ///
+    /// ## 错误处理
+    ///
+    /// * 文件权限掩码无效时，返回 [DaemonError::InvalidUmask] 错误。
+    /// * 当前进程已经创建了多个线程时，返回 [DaemonError::MultiThreaded] 错误。
+    /// * 无法打开标准输入输出文件时，返回 [DaemonError::OpenStdio] 错误。
+    /// * 无法获取 PID 文件时，返回 [DaemonError::Pid] 错误。
+    /// * 第一次 `fork` 失败时，返回 [DaemonError::Fork] 错误。
+    /// * 无法回收第一个子进程时，返回 [DaemonError::Wait] 错误。
+    /// * 之后的步骤失败时，返回包含失败原因的 [DaemonError::Startup] 错误。
+    pub fn start(self) -> Result<Option<PidFileGuard>, DaemonError> {
+        if let Some(umask) = self.umask
+            && umask > MAX_UMASK
+        {
+            return Err(DaemonError::InvalidUmask(umask));
+        }
+        // fork 只复制调用它的线程，其它线程持有的锁在子进程中永远不会被释放
+        if let Some(threads) = read_thread_count()
+            && threads > 1
+        {
+            return Err(DaemonError::MultiThreaded(threads));
+        }
+
+        // 在原进程中打开文件，错误可以直接返回给调用者
+        let stdio = self.open_stdio_files()?;
+        let mut pid_file_guard = match &self.pid_file {
+            Some(path) => {
+                let path = std::path::absolute(path)
+                    .map_err(|_| PidError::InvalidPidFilePath(path.clone()))?;
+                let mode = self.pid_file_mode.unwrap_or(DEFAULT_PID_FILE_MODE);
+                Some(PidFileGuard::with_mode(path, mode)?)
+            }
+            None => None,
+        };
+
+        let (reader, writer) = io::pipe().map_err(DaemonError::Fork)?;
+        if let Some(child) = fork()? {
+            // 原进程
+            drop(writer);
+            wait_for_daemon(child, reader)?;
+            debug!("daemon process started");
+            std::process::exit(0);
+        }
+        drop(reader);
+
+        // 第一个子进程，创建新会话后再次 fork
+        match setsid().and_then(|_| fork()) {
+            // SAFETY: _exit 直接结束进程，不执行析构函数和退出处理函数，避免重复刷新继承自原进程的缓冲区
+            Ok(Some(_)) => unsafe { libc::_exit(0) },
+            Ok(None) => {}
+            Err(e) => report_failure(writer, e),
+        }
+
+        // 守护进程
+        match self.setup(&stdio, pid_file_guard.as_mut()) {
+            Ok(()) => {
+                let mut writer = writer;
+                let _ = writer.write_all(&[READY]);
+                Ok(pid_file_guard)
+            }
+            Err(e) => report_failure(writer, e),
+        }
+    }
+
+    /// # 打开标准输入输出文件
+    ///
+    /// 指定了文件权限掩码时，在打开期间临时应用该掩码，使新建的输出文件与守护进程之后创建的文件权限一致，
+    /// 打开后恢复原进程的掩码。
+    fn open_stdio_files(&self) -> Result<[File; 3], DaemonError> {
+        // SAFETY: umask 总是成功，只修改当前进程的文件权限掩码
+        let previous_umask = self
+            .umask
+            .map(|umask| unsafe { libc::umask(umask as libc::mode_t) });
+        let stdio = (|| {
+            Ok([
+                open_stdio(None, false)?,
+                open_stdio(self.stdout.as_deref(), true)?,
+                open_stdio(self.stderr.as_deref(), true)?,
+            ])
+        })();
+        if let Some(previous_umask) = previous_umask {
+            // SAFETY: 同上，恢复原进程的文件权限掩码
+            unsafe {
+                libc::umask(previous_umask);
+            }
+        }
+        stdio
+    }
+
+    /// # 在守护进程中完成初始化
+    fn setup(
+        &self,
+        stdio: &[File; 3],
+        pid_file_guard: Option<&mut PidFileGuard>,
+    ) -> Result<(), DaemonError> {
+        if let Some(working_directory) = &self.working_directory {
+            std::env::set_current_dir(working_directory)
+                .map_err(|e| DaemonError::ChangeDirectory(working_directory.clone(), e))?;
+        }
+        if let Some(umask) = self.umask {
+            // SAFETY: umask 总是成功，只修改当前进程的文件权限掩码
+            unsafe {
+                libc::umask(umask as libc::mode_t);
+            }
+        }
+        for (file, fd) in
+            stdio
+                .iter()
+                .zip([libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO])
+        {
+            // SAFETY: 两个文件描述符都有效，dup2 会原子地关闭并替换目标文件描述符
+            if unsafe { libc::dup2(file.as_raw_fd(), fd) } == -1 {
+                return Err(DaemonError::RedirectStdio(io::Error::last_os_error()));
+            }
+        }
+        if let Some(pid_file_guard) = pid_file_guard {
+            pid_file_guard.update_pid()?;
+        }
+        Ok(())
+    }
+}
+
+/// # 读取当前进程的线程数
+///
+/// 从 `/proc/self/status` 中读取 `Threads:` 字段，无法读取时返回 `None`。
+fn read_thread_count() -> Option<usize> {
+    let status = fs::read_to_string(PROC_SELF_STATUS).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// # 打开标准输入输出文件
+///
+/// 未指定路径时打开 `/dev/null`，`write` 为是否以追加写入的方式打开。
+fn open_stdio(path: Option<&Path>, write: bool) -> Result<File, DaemonError> {
+    let path = path.unwrap_or(Path::new(DEV_NULL));
+    let mut options = OpenOptions::new();
+    if write {
+        options.append(true).create(true);
+    } else {
+        options.read(true);
+    }
+    options
+        .open(path)
+        .map_err(|e| DaemonError::OpenStdio(path.to_path_buf(), e))
+}
+
+/// # 创建子进程
+///
+/// 在父进程中返回子进程的 PID，在子进程中返回 `None`。
+fn fork() -> Result<Option<libc::pid_t>, DaemonError> {
+    // SAFETY: 调用者保证此时只有一个线程，子进程中的内存和锁的状态都是一致的
+    match unsafe { libc::fork() } {
+        -1 => Err(DaemonError::Fork(io::Error::last_os_error())),
+        0 => Ok(None),
+        pid => Ok(Some(pid)),
+    }
+}
+
+/// # 创建新会话
+fn setsid() -> Result<(), DaemonError> {
+    // SAFETY: setsid 只修改当前进程的会话和进程组
+    if unsafe { libc::setsid() } == -1 {
+        return Err(DaemonError::Setsid(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// # 等待守护进程报告启动结果
+///
+/// 回收第一个子进程后读取管道，直到守护进程发送启动成功的标记或管道被关闭。
+/// 管道中没有任何报告时，第一个子进程的异常退出状态作为启动失败的原因。
+fn wait_for_daemon(child: libc::pid_t, mut reader: PipeReader) -> Result<(), DaemonError> {
+    let status = wait_child(child)?;
+    let mut report = Vec::new();
+    reader
+        .read_to_end(&mut report)
+        .map_err(|e| DaemonError::Startup(e.to_string()))?;
+    match report.as_slice() {
+        [READY] => Ok(()),
+        [] if !status.success() => Err(DaemonError::Startup(format!(
+            "first child process exited with {status}"
+        ))),
+        [] => Err(DaemonError::Startup(
+            "daemon process exited unexpectedly".to_string(),
+        )),
+        message => Err(DaemonError::Startup(
+            String::from_utf8_lossy(message).into_owned(),
+        )),
+    }
+}
+
+/// # 回收子进程
+///
+/// 被信号中断时重试，返回子进程的退出状态。
+fn wait_child(child: libc::pid_t) -> Result<ExitStatus, DaemonError> {
+    let mut status = 0;
+    loop {
+        // SAFETY: child 是当前进程的子进程，status 指向有效的内存
+        if unsafe { libc::waitpid(child, &mut status, 0) } != -1 {
+            return Ok(ExitStatus::from_raw(status));
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(DaemonError::Wait(e));
+        }
+    }
+}
+
+/// # 向原进程报告启动失败并退出
+///
+/// 错误的来源无法跨进程传递，报告中依次拼接错误及其全部来源的信息。
+fn report_failure(mut writer: PipeWriter, error: DaemonError) -> ! {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+    let _ = writer.write_all(message.as_bytes());
+    drop(writer);
+    // SAFETY: _exit 直接结束进程，PID 文件守卫不会被析构，由原进程负责清理
+    unsafe { libc::_exit(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// 测试已经创建了其它线程时拒绝守护进程化
+    #[test]
+    fn test_reject_multi_threaded_process() {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || receiver.recv());
+        let result = Daemonize::new().start();
+        drop(sender);
+        thread.join().unwrap().unwrap_err();
+        assert!(matches!(result, Err(DaemonError::MultiThreaded(threads)) if threads > 1));
+    }
+}
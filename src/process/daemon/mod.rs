@@ -0,0 +1,7 @@
+//! # 守护进程模块
+//!
+//! 提供将当前进程转换为守护进程的功能，包括两次 `fork`、创建新会话、切换工作目录、
+//! 设置文件权限掩码、重定向标准输入输出，以及记录守护进程的 PID 文件。
+
+pub(super) mod daemon_error;
+pub(super) mod daemonize;
@@ -1,12 +1,20 @@
+mod daemon;
 mod pid;
 mod process;
 mod signal;
+mod user;
 
 // 重新导出结构体，简化外部引用
+pub use daemon::daemon_error::*;
+pub use daemon::daemonize::*;
 pub use pid::pid_error::*;
 pub use pid::pid_file_guard::*;
+pub use pid::pid_file_status::*;
 pub use pid::pid_utils::*;
 pub use process::process_error::*;
 pub use process::process_utils::*;
 pub use signal::signal_error::*;
 pub use signal::signal_utils::*;
+pub use user::credentials::*;
+pub use user::user_error::*;
+pub use user::user_utils::*;
@@ -1,8 +1,9 @@
 //! # PID 管理模块
 //!
-//! 提供进程ID相关的管理功能，包括PID文件的读取、写入和删除操作。
+//! 提供进程ID相关的管理功能，包括PID文件的读取、写入、删除和状态检查操作。
 //! 主要用于确保进程的唯一性和状态跟踪。
 
 pub(super) mod pid_error;
 pub(super) mod pid_file_guard;
+pub(super) mod pid_file_status;
 pub(super) mod pid_utils;
@@ -2,7 +2,10 @@
 //!
 //! 该模块定义了处理 PID 文件时可能遇到的各种错误类型，
 //! 包括路径无效、文件操作失败、内容解析错误等情况。
+//! 文件操作失败的错误会保留文件路径和底层的 [io::Error]，可以通过 [io::Error::kind] 区分
+//! 权限不足（`PermissionDenied`）、文件不存在（`NotFound`）等原因。
 
+use std::io;
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -19,27 +22,33 @@ pub enum PidError {
 
     /// 打开 PID 文件失败错误
     ///
-    /// 当无法打开指定的 PID 文件时返回此错误
-    #[error("Fail to open PID file: {0}")]
-    OpenPidFile(String),
+    /// 当无法打开指定的 PID 文件时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to open PID file {0}")]
+    OpenPidFile(String, #[source] io::Error),
 
     /// 创建 PID 文件失败错误
     ///
-    /// 当无法创建新的 PID 文件时返回此错误
-    #[error("Fail to create PID file: {0}")]
-    CreatePidFile(String),
+    /// 当无法创建新的 PID 文件时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to create PID file {0}")]
+    CreatePidFile(String, #[source] io::Error),
+
+    /// 创建 PID 文件所在目录失败错误
+    ///
+    /// 当 PID 文件的父目录不存在且无法创建时返回此错误，包含目录路径和底层的 I/O 错误
+    #[error("Fail to create directory of PID file {0}")]
+    CreatePidDir(String, #[source] io::Error),
 
     /// 读取 PID 文件失败错误
     ///
-    /// 当无法读取 PID 文件内容时返回此错误
-    #[error("Fail to read PID file: {0}")]
-    ReadPidFile(String),
+    /// 当无法读取 PID 文件内容时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to read PID file {0}")]
+    ReadPidFile(String, #[source] io::Error),
 
     /// 写入 PID 文件失败错误
     ///
-    /// 当无法向 PID 文件写入数据时返回此错误
-    #[error("Fail to write PID file: {0}")]
-    WritePidFile(String),
+    /// 当无法向 PID 文件写入数据时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to write PID file {0}")]
+    WritePidFile(String, #[source] io::Error),
 
     /// 解析 PID 文件内容失败错误
     ///
@@ -49,7 +58,20 @@ pub enum PidError {
 
     /// 删除 PID 文件失败错误
     ///
-    /// 当无法删除指定的 PID 文件时返回此错误
-    #[error("Fail to delete PID file: {0}")]
-    DeletePidFile(String),
+    /// 当无法删除指定的 PID 文件时返回此错误，包含文件路径和底层的 I/O 错误
+    #[error("Fail to delete PID file {0}")]
+    DeletePidFile(String, #[source] io::Error),
+
+    /// 锁定 PID 文件失败错误
+    ///
+    /// 当无法对 PID 文件加锁，或文件已被其他进程锁定但无法读取持有者的 PID 时返回此错误，包含文件路径和底层的 I/O 错误。
+    /// 文件已被锁定时，I/O 错误的类型为 [io::ErrorKind::WouldBlock]
+    #[error("Fail to lock PID file {0}")]
+    LockPidFile(String, #[source] io::Error),
+
+    /// 已有实例在运行错误
+    ///
+    /// 当 PID 文件被另一个存活的进程持有时返回此错误，包含持有者的 PID
+    #[error("Another instance is already running with PID: {0}")]
+    AlreadyRunning(u32),
 }
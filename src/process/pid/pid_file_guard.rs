@@ -1,27 +1,37 @@
 //! # PID 文件守卫模块
 //!
 //! 本模块提供了 `PidFileGuard` 结构体，用于管理 PID 文件的生命周期。
+//! 守卫在存活期间持有 PID 文件的排他锁，保证同一时间只有一个实例运行；
 //! 在对象被销毁时会自动清理对应的 PID 文件，避免残留文件占用资源。
 
-use crate::process::pid::pid_utils::delete_pid_file_if_my_process;
-use crate::process::{write_pid, PidError};
-use tracing::warn;
+use crate::process::PidError;
+use crate::process::pid::pid_file_status::{PidFileInfo, PidFileStatus};
+use crate::process::pid::pid_utils::{
+    DEFAULT_PID_FILE_MODE, delete_pid_file_if_my_process, get_current_pid,
+};
+use std::fs::{self, File, OpenOptions, Permissions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt};
 use std::path::PathBuf;
+use tracing::{debug, info, warn};
 
 /// # PID 文件守卫
 ///
 /// 用于管理 PID 文件的生命周期，在对象被销毁时自动清理 PID 文件。
+/// 守卫持有 PID 文件的排他锁（`flock`），锁随守卫一起释放，进程异常退出时由操作系统自动释放。
 /// 通过实现 `Drop` trait，确保在作用域结束时自动执行清理逻辑。
 #[derive(Debug)]
 pub struct PidFileGuard {
     /// 存储 PID 文件的路径
     pid_file_path: PathBuf,
+    /// 持有排他锁的 PID 文件
+    pid_file: File,
 }
 
 impl Drop for PidFileGuard {
     /// # 自动清理 PID 文件
     ///
-    /// 当 `PidFileGuard` 超出作用域时自动调用此方法，尝试删除对应的 PID 文件。
+    /// 当 `PidFileGuard` 超出作用域时自动调用此方法，在释放锁之前尝试删除对应的 PID 文件。
     /// 如果删除失败，会记录警告日志但不会 panic。
     fn drop(&mut self) {
         if let Err(e) = delete_pid_file_if_my_process(&self.pid_file_path) {
@@ -33,28 +43,173 @@ impl Drop for PidFileGuard {
 impl PidFileGuard {
     /// # 创建新的 PID 文件守卫实例
     ///
-    /// 构造一个新的 `PidFileGuard` 实例，并创建对应的 PID 文件。
+    /// 打开（不存在时创建）PID 文件并获取其排他锁，然后写入当前进程的 PID 及其启动时间、可执行文件路径。
+    /// 父目录不存在时会自动创建，文件权限为 `0644`，需要指定权限时请使用 [PidFileGuard::with_mode]。
+    ///
+    /// * 文件已被其他实例锁定时，返回持有者的 PID。
+    /// * 文件未被锁定但记录的进程仍在运行（如未使用锁的旧版本实例）时，同样视为已有实例在运行。
+    /// * 文件记录的进程已不存在或其 PID 已被复用时，视为残留文件，直接接管，详见 [PidFileInfo::status]。
     ///
     /// ## 参数
-    /// - `app_file_path`: 应用程序文件的基础路径，用于生成 `.pid` 文件路径。
+    /// - `pid_file_path`: PID 文件的路径。
     ///
     /// ## 返回值
     /// - 成功时返回 `Ok(PidFileGuard)` 实例。
-    /// - 失败时返回 `Err(PidError)`，表示无法创建或写入 PID 文件。
+    /// - 失败时返回 `Err(PidError)`。
+    ///
+    /// ## 错误类型
+    /// - `InvalidPidFilePath`: 路径无效。
+    /// - `CreatePidDir`: 创建父目录失败。
+    /// - `OpenPidFile`: 无法打开或创建文件。
+    /// - `LockPidFile`: 无法加锁，或文件已被锁定但无法读取持有者的 PID。
+    /// - `AlreadyRunning`: 另一个存活的实例持有 PID 文件。
+    /// - `ReadPidFile` / `WritePidFile`: 读写文件失败。
     ///
     /// ## 示例
     /// ```rust
-    /// use std::path::PathBuf;
-    /// use crate::process::pid::PidFileGuard;
+    /// use wheel_rs::process::{PidError, PidFileGuard};
+    ///
+    /// let pid_file_path = std::env::temp_dir().join(format!("guard-{}.pid", std::process::id()));
+    /// let guard = PidFileGuard::new(pid_file_path.clone()).unwrap();
+    ///
+    /// // 守卫存活期间，再次获取会失败并返回持有者的 PID
+    /// let result = PidFileGuard::new(pid_file_path.clone());
+    /// assert!(matches!(result, Err(PidError::AlreadyRunning(pid)) if pid == std::process::id()));
     ///
-    /// let app_path = PathBuf::from("/tmp/my_app");
-    /// let guard = PidFileGuard::new(&app_path);
+    /// // 守卫销毁后 PID 文件被删除
+    /// drop(guard);
+    /// assert!(!pid_file_path.exists());
     /// ```
     pub fn new(pid_file_path: PathBuf) -> Result<Self, PidError> {
-        // 写入当前进程的 PID 到文件中
-        write_pid(&pid_file_path)?;
+        Self::with_mode(pid_file_path, DEFAULT_PID_FILE_MODE)
+    }
+
+    /// # 以指定的文件权限创建 PID 文件守卫实例
+    ///
+    /// 守卫需要持有 PID 文件本身的锁，不能像 [write_pid_with_mode](crate::process::write_pid_with_mode)
+    /// 那样通过重命名替换文件，写入后会将内容同步到磁盘。其余行为同 [PidFileGuard::new]。
+    ///
+    /// ## 参数
+    /// - `pid_file_path`: PID 文件的路径。
+    /// - `mode`: PID 文件的权限，如 `0o644`，不受进程 umask 的影响。
+    ///
+    /// ## 返回值
+    /// - 成功时返回 `Ok(PidFileGuard)` 实例。
+    /// - 失败时返回 `Err(PidError)`。
+    ///
+    /// ## 错误类型
+    /// - 同 [PidFileGuard::new]。
+    pub fn with_mode(pid_file_path: PathBuf, mode: u32) -> Result<Self, PidError> {
+        // 验证路径是否有效
+        let path = pid_file_path
+            .to_str()
+            .ok_or(PidError::InvalidPidFilePath(pid_file_path.clone()))?
+            .to_string();
+
+        // 创建父目录
+        if let Some(dir) = pid_file_path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)
+                .map_err(|e| PidError::CreatePidDir(dir.display().to_string(), e))?;
+        }
+
+        let mut pid_file = loop {
+            let mut pid_file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .mode(mode)
+                .open(&pid_file_path)
+                .map_err(|e| PidError::OpenPidFile(path.clone(), e))?;
+            match pid_file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => {
+                    return Err(match read_holder(&mut pid_file) {
+                        Some(holder) => PidError::AlreadyRunning(holder.pid),
+                        None => PidError::LockPidFile(path, TryLockError::WouldBlock.into()),
+                    });
+                }
+                Err(TryLockError::Error(e)) => return Err(PidError::LockPidFile(path, e)),
+            }
+            // 加锁前文件可能已被上一个持有者删除，此时锁住的是已删除的文件，需要重新打开
+            if is_same_file(&pid_file, &pid_file_path) {
+                break pid_file;
+            }
+            debug!("PID file {path} was replaced while locking, retrying...");
+        };
+
+        // 已获取锁，检查文件中记录的进程是否仍在运行
+        if let Some(holder) = read_holder(&mut pid_file)
+            && holder.pid != get_current_pid()
+        {
+            match holder.status() {
+                PidFileStatus::RunningOurs(pid) | PidFileStatus::RunningOther(pid) => {
+                    return Err(PidError::AlreadyRunning(pid));
+                }
+                _ => info!(
+                    "Taking over stale PID file {path} of process {}",
+                    holder.pid
+                ),
+            }
+        }
+
+        // 写入当前进程的 PID 及元数据
+        pid_file
+            .set_permissions(Permissions::from_mode(mode))
+            .map_err(|e| PidError::WritePidFile(path.clone(), e))?;
+        write_current_pid(&mut pid_file, &path)?;
 
         // 返回成功的守卫实例
-        Ok(Self { pid_file_path })
+        Ok(Self {
+            pid_file_path,
+            pid_file,
+        })
+    }
+
+    /// # 将 PID 文件更新为当前进程的 PID
+    ///
+    /// 排他锁随文件描述符在 `fork` 时被子进程继承，守护进程化时由父进程获取守卫，
+    /// 再由最终的守护进程调用此方法记录自己的 PID。
+    pub(crate) fn update_pid(&mut self) -> Result<(), PidError> {
+        let path = self
+            .pid_file_path
+            .to_str()
+            .ok_or(PidError::InvalidPidFilePath(self.pid_file_path.clone()))?
+            .to_string();
+        write_current_pid(&mut self.pid_file, &path)
+    }
+}
+
+/// # 写入当前进程的 PID 及元数据
+///
+/// 清空文件后写入，并将内容同步到磁盘。
+fn write_current_pid(pid_file: &mut File, path: &str) -> Result<(), PidError> {
+    let info = PidFileInfo::current();
+    debug!("Writing PID {} to {path}...", info.pid);
+    pid_file
+        .set_len(0)
+        .and_then(|_| pid_file.seek(SeekFrom::Start(0)))
+        .and_then(|_| pid_file.write_all(info.to_content().as_bytes()))
+        .and_then(|_| pid_file.sync_data())
+        .map_err(|e| PidError::WritePidFile(path.to_string(), e))
+}
+
+/// # 读取文件中记录的持有者信息
+///
+/// 文件为空或内容无效时返回 `None`。
+fn read_holder(pid_file: &mut File) -> Option<PidFileInfo> {
+    let mut content = String::new();
+    pid_file.seek(SeekFrom::Start(0)).ok()?;
+    pid_file.read_to_string(&mut content).ok()?;
+    PidFileInfo::parse(&content)
+}
+
+/// # 检查已打开的文件与路径指向的文件是否相同
+fn is_same_file(file: &File, path: &PathBuf) -> bool {
+    match (file.metadata(), std::fs::metadata(path)) {
+        (Ok(opened), Ok(current)) => opened.dev() == current.dev() && opened.ino() == current.ino(),
+        _ => false,
     }
 }
@@ -0,0 +1,268 @@
+//! # PID 文件状态模块
+//!
+//! PID 会被操作系统复用，仅凭 PID 文件中记录的进程ID无法判断它是否仍然属于本应用。
+//! 本模块在 PID 文件中额外记录进程的启动时间和可执行文件路径，并通过 `/proc/<pid>` 进行核对，
+//! 从而区分残留的 PID 文件、本应用正在运行的实例和复用了该 PID 的其他进程。
+//!
+//! PID 文件的格式如下，第一行始终为 PID，与 [read_pid](crate::process::read_pid) 保持兼容：
+//!
+//! ```text
+//! 12345
+//! start_time=8765432
+//! exe=/usr/local/bin/my_app
+//! ```
+
+use crate::process::pid::pid_utils::{delete_pid_file, get_current_pid};
+use crate::process::process::process_utils::is_valid_pid;
+use crate::process::{PidError, ProcessError, check_process};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+/// 启动时间元数据的键
+const START_TIME_KEY: &str = "start_time";
+/// 可执行文件路径元数据的键
+const EXE_KEY: &str = "exe";
+/// 可执行文件被删除或替换后，`/proc/<pid>/exe` 链接目标的后缀
+const DELETED_SUFFIX: &str = " (deleted)";
+
+/// # PID 文件状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PidFileStatus {
+    /// PID 文件不存在
+    Missing,
+    /// PID 文件残留，记录的进程已不存在或其 PID 已被其他进程复用
+    Stale(u32),
+    /// 记录的进程正在运行，且是本应用的进程
+    RunningOurs(u32),
+    /// 记录的进程正在运行，但不是本应用的进程
+    RunningOther(u32),
+}
+
+/// # PID 文件内容
+///
+/// PID 文件中记录的进程ID及用于识别进程的元数据。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PidFileInfo {
+    /// 进程ID
+    pub pid: u32,
+    /// 进程的启动时间，为系统启动后经过的时钟周期数（`/proc/<pid>/stat` 的第 22 个字段）
+    pub start_time: Option<u64>,
+    /// 进程的可执行文件路径
+    pub exe: Option<PathBuf>,
+}
+
+impl PidFileInfo {
+    /// # 获取当前进程的 PID 文件内容
+    pub fn current() -> Self {
+        let pid = get_current_pid();
+        Self {
+            pid,
+            start_time: read_start_time(pid),
+            exe: std::env::current_exe().ok(),
+        }
+    }
+
+    /// # 解析 PID 文件内容
+    ///
+    /// 第一行不是有效的 PID（包括 0 和超过 `i32::MAX` 的值）时返回 `None`，无法识别的元数据会被忽略。
+    pub fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let pid = lines
+            .next()?
+            .trim()
+            .parse()
+            .ok()
+            .filter(|pid| is_valid_pid(*pid))?;
+        let mut info = Self {
+            pid,
+            start_time: None,
+            exe: None,
+        };
+        for line in lines {
+            match line.split_once('=') {
+                Some((START_TIME_KEY, value)) => info.start_time = value.trim().parse().ok(),
+                Some((EXE_KEY, value)) => info.exe = Some(PathBuf::from(value)),
+                _ => {}
+            }
+        }
+        Some(info)
+    }
+
+    /// # 转换为 PID 文件内容
+    pub fn to_content(&self) -> String {
+        let mut content = self.pid.to_string();
+        if let Some(start_time) = self.start_time {
+            content.push_str(&format!("\n{START_TIME_KEY}={start_time}"));
+        }
+        if let Some(exe) = &self.exe {
+            content.push_str(&format!("\n{EXE_KEY}={}", exe.display()));
+        }
+        content.push('\n');
+        content
+    }
+
+    /// # 检查记录的进程的状态
+    ///
+    /// * PID 无效、进程不存在，或者记录了启动时间但与进程当前的启动时间不一致（PID 已被复用）时，返回 [PidFileStatus::Stale]。
+    /// * 进程的可执行文件与当前进程的可执行文件相同时，返回 [PidFileStatus::RunningOurs]。
+    /// * 否则（包括无法确定进程的可执行文件时）返回 [PidFileStatus::RunningOther]。
+    pub fn status(&self) -> PidFileStatus {
+        let pid = self.pid;
+        match check_process(pid) {
+            Ok(false) | Err(ProcessError::InvalidPid(_)) => return PidFileStatus::Stale(pid),
+            _ => {}
+        }
+        if let Some(recorded) = self.start_time
+            && let Some(actual) = read_start_time(pid)
+            && recorded != actual
+        {
+            debug!("PID {pid} was reused: start time {recorded} != {actual}");
+            return PidFileStatus::Stale(pid);
+        }
+        let exe = read_exe(pid).or_else(|| self.exe.clone());
+        match (exe, std::env::current_exe()) {
+            (Some(exe), Ok(current_exe)) if exe == current_exe => PidFileStatus::RunningOurs(pid),
+            _ => PidFileStatus::RunningOther(pid),
+        }
+    }
+}
+
+/// # 检查 PID 文件的状态
+///
+/// 读取 PID 文件并核对其中记录的进程，判断 PID 文件是否残留、是否属于本应用。
+///
+/// ## 参数
+/// - `pid_file_path`: PID文件的路径。
+///
+/// ## 返回值
+/// - `Ok(PidFileStatus)`: PID 文件的状态，详见 [PidFileInfo::status]。
+/// - `Err(PidError)`: 读取文件失败或内容无效。
+///
+/// ## 错误类型
+/// - `InvalidPidFilePath`: 路径无效。
+/// - `ReadPidFile`: 读取文件失败。
+/// - `ParsePidFileContent`: 解析PID内容失败。
+///
+/// ## 示例
+/// ```
+/// use wheel_rs::process::{PidFileStatus, inspect_pid_file, write_pid};
+///
+/// let pid_file_path = std::env::temp_dir().join(format!("status-{}.pid", std::process::id()));
+/// assert_eq!(inspect_pid_file(&pid_file_path).unwrap(), PidFileStatus::Missing);
+///
+/// write_pid(&pid_file_path).unwrap();
+/// assert_eq!(
+///     inspect_pid_file(&pid_file_path).unwrap(),
+///     PidFileStatus::RunningOurs(std::process::id())
+/// );
+/// std::fs::remove_file(&pid_file_path).unwrap();
+/// ```
+pub fn inspect_pid_file(pid_file_path: &PathBuf) -> Result<PidFileStatus, PidError> {
+    // 验证路径是否有效
+    let path = pid_file_path
+        .to_str()
+        .ok_or(PidError::InvalidPidFilePath(pid_file_path.clone()))?;
+
+    let content = match fs::read_to_string(pid_file_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(PidFileStatus::Missing),
+        Err(e) => return Err(PidError::ReadPidFile(path.to_string(), e)),
+    };
+    let info =
+        PidFileInfo::parse(&content).ok_or(PidError::ParsePidFileContent(path.to_string()))?;
+    Ok(info.status())
+}
+
+/// # 删除残留的 PID 文件
+///
+/// PID 文件的状态为 [PidFileStatus::Stale] 时删除该文件，用于在启动前清理上次异常退出留下的文件。
+///
+/// ## 参数
+/// - `pid_file_path`: PID文件的路径。
+///
+/// ## 返回值
+/// - `Ok(PidFileStatus)`: 删除前 PID 文件的状态。
+/// - `Err(PidError)`: 检查或删除文件失败。
+///
+/// ## 错误类型
+/// - 继承自 `inspect_pid_file` 和 `delete_pid_file` 的错误类型。
+pub fn remove_stale_pid_file(pid_file_path: &PathBuf) -> Result<PidFileStatus, PidError> {
+    let status = inspect_pid_file(pid_file_path)?;
+    if let PidFileStatus::Stale(pid) = status {
+        info!("Removing stale PID file {pid_file_path:?} of process {pid}");
+        delete_pid_file(pid_file_path)?;
+    }
+    Ok(status)
+}
+
+/// # 读取进程的启动时间
+///
+/// 从 `/proc/<pid>/stat` 中读取第 22 个字段，进程不存在或无法读取时返回 `None`。
+fn read_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // 第 2 个字段是括号包围的进程名，可能包含空格，从最后一个右括号之后开始计数
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// # 读取进程的可执行文件路径
+///
+/// 读取 `/proc/<pid>/exe` 链接的目标，进程不存在或没有权限时返回 `None`。
+fn read_exe(pid: u32) -> Option<PathBuf> {
+    let exe = fs::read_link(format!("/proc/{pid}/exe")).ok()?;
+    match exe
+        .to_str()
+        .and_then(|exe| exe.strip_suffix(DELETED_SUFFIX))
+    {
+        Some(exe) => Some(PathBuf::from(exe)),
+        None => Some(exe),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_round_trip() {
+        let info = PidFileInfo {
+            pid: 42,
+            start_time: Some(123),
+            exe: Some(PathBuf::from("/usr/bin/app")),
+        };
+        let content = info.to_content();
+        assert_eq!(content, "42\nstart_time=123\nexe=/usr/bin/app\n");
+        assert_eq!(PidFileInfo::parse(&content), Some(info));
+    }
+
+    #[test]
+    fn test_parse_legacy_content() {
+        let info = PidFileInfo::parse("42").unwrap();
+        assert_eq!(info.pid, 42);
+        assert_eq!(info.start_time, None);
+        assert!(PidFileInfo::parse("not a pid").is_none());
+        assert!(PidFileInfo::parse("0").is_none());
+        assert!(PidFileInfo::parse(&(i32::MAX as u32 + 1).to_string()).is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        assert_eq!(
+            PidFileInfo::current().status(),
+            PidFileStatus::RunningOurs(get_current_pid())
+        );
+        let reused = PidFileInfo {
+            start_time: PidFileInfo::current().start_time.map(|time| time + 1),
+            ..PidFileInfo::current()
+        };
+        assert_eq!(reused.status(), PidFileStatus::Stale(get_current_pid()));
+        let other = PidFileInfo {
+            pid: 1,
+            start_time: None,
+            exe: None,
+        };
+        assert_eq!(other.status(), PidFileStatus::RunningOther(1));
+    }
+}
@@ -4,12 +4,20 @@
 //! 包括PID文件的读取、写入、删除以及进程身份验证等功能。
 
 use crate::process::PidError;
+use crate::process::pid::pid_file_status::PidFileInfo;
+use crate::process::process::process_utils::is_valid_pid;
 use tracing::{debug, info};
-use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions, Permissions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 use std::process;
 
+/// PID文件的默认权限
+pub const DEFAULT_PID_FILE_MODE: u32 = 0o644;
+/// 临时文件已存在时，删除后重新创建的最大次数
+const MAX_TEMP_FILE_RETRIES: u32 = 3;
+
 pub fn get_current_pid() -> u32 {
     process::id()
 }
@@ -58,9 +66,9 @@ pub fn get_pid_file_path(app_file_path: &PathBuf) -> PathBuf {
 ///
 /// ## 错误类型
 /// - `InvalidPidFilePath`: 路径无效。
-/// - `OpenPidFileError`: 无法打开文件。
-/// - `ReadPidFileError`: 读取文件失败。
-/// - `ParsePidFileContentError`: 解析PID内容失败。
+/// - `OpenPidFile`: 无法打开文件。
+/// - `ReadPidFile`: 读取文件失败。
+/// - `ParsePidFileContent`: 文件为空、解析PID内容失败或PID无效（0 或超过 `i32::MAX`）。
 pub fn read_pid(pid_file_path: &PathBuf) -> Result<Option<u32>, PidError> {
     debug!("Reading PID from {pid_file_path:?}...");
 
@@ -75,16 +83,18 @@ pub fn read_pid(pid_file_path: &PathBuf) -> Result<Option<u32>, PidError> {
     }
 
     // 打开文件并读取第一行内容
-    let pid_file = File::open(path).map_err(|_| PidError::OpenPidFile(path.to_string()))?;
+    let pid_file = File::open(path).map_err(|e| PidError::OpenPidFile(path.to_string(), e))?;
     let reader = BufReader::new(pid_file);
     let pid = reader
         .lines()
         .next()
-        .ok_or(PidError::ReadPidFile(path.to_string()))?
-        .map_err(|_| PidError::ReadPidFile(path.to_string()))?
+        .ok_or(PidError::ParsePidFileContent(path.to_string()))?
+        .map_err(|e| PidError::ReadPidFile(path.to_string(), e))?
         .trim()
         .parse::<u32>()
-        .map_err(|_| PidError::ParsePidFileContent(path.to_string()))?;
+        .ok()
+        .filter(|pid| is_valid_pid(*pid))
+        .ok_or(PidError::ParsePidFileContent(path.to_string()))?;
 
     Ok(Some(pid))
 }
@@ -92,6 +102,8 @@ pub fn read_pid(pid_file_path: &PathBuf) -> Result<Option<u32>, PidError> {
 /// # 将当前进程ID写入PID文件
 ///
 /// 创建或覆盖指定路径的PID文件，并将当前进程的ID写入其中。该操作通常用于标识进程的唯一性。
+/// 进程ID之后会记录进程的启动时间和可执行文件路径，用于检测 PID 复用，详见 [PidFileInfo]。
+/// 文件权限为 `0644`，需要指定权限时请使用 [write_pid_with_mode]。
 ///
 /// ## 参数
 /// - `pid_file_path`: PID文件的路径。
@@ -103,31 +115,122 @@ pub fn read_pid(pid_file_path: &PathBuf) -> Result<Option<u32>, PidError> {
 /// ## 注意事项
 /// - 若文件已存在，会被覆盖。
 /// - 确保调用者具有足够的文件系统权限。
-/// - 并发访问可能导致冲突，请谨慎使用。
+/// - 并发访问可能导致冲突，请谨慎使用。需要保证只有一个实例运行时，请使用 [PidFileGuard](crate::process::PidFileGuard)。
 ///
 /// ## 错误类型
-/// - `InvalidPidFilePath`: 路径无效。
-/// - `CreatePidFileError`: 创建文件失败。
-/// - `WritePidFileError`: 写入文件失败。
+/// - 同 [write_pid_with_mode]。
 pub fn write_pid(pid_file_path: &PathBuf) -> Result<(), PidError> {
-    let pid = get_current_pid();
-    debug!("Writing PID {pid} to {pid_file_path:?}...");
+    write_pid_with_mode(pid_file_path, DEFAULT_PID_FILE_MODE)
+}
+
+/// # 以指定的文件权限将当前进程ID写入PID文件
+///
+/// 先将内容写入同一目录下的临时文件并同步到磁盘，再通过重命名原子地替换PID文件，
+/// 进程在写入过程中崩溃也不会留下空的或不完整的PID文件。父目录不存在时会自动创建。
+///
+/// ## 参数
+/// - `pid_file_path`: PID文件的路径。
+/// - `mode`: PID文件的权限，如 `0o644`，不受进程 umask 的影响。
+///
+/// ## 返回值
+/// - `Ok(())`: 成功写入PID文件。
+/// - `Err(PidError)`: 发生I/O错误或其他异常。
+///
+/// ## 错误类型
+/// - `InvalidPidFilePath`: 路径无效。
+/// - `CreatePidDir`: 创建父目录失败。
+/// - `CreatePidFile`: 创建临时文件失败。
+/// - `WritePidFile`: 写入、同步或重命名文件失败。
+///
+/// ## 示例
+/// ```
+/// use std::os::unix::fs::PermissionsExt;
+/// use wheel_rs::process::{read_pid, write_pid_with_mode};
+///
+/// let dir = std::env::temp_dir().join(format!("wheel-rs-pid-{}", std::process::id()));
+/// let pid_file_path = dir.join("run").join("app.pid");
+/// write_pid_with_mode(&pid_file_path, 0o600).unwrap();
+///
+/// let content = std::fs::read_to_string(&pid_file_path).unwrap();
+/// assert!(content.ends_with('\n'));
+/// assert_eq!(read_pid(&pid_file_path).unwrap(), Some(std::process::id()));
+/// let mode = std::fs::metadata(&pid_file_path).unwrap().permissions().mode();
+/// assert_eq!(mode & 0o777, 0o600);
+/// std::fs::remove_dir_all(dir).unwrap();
+/// ```
+pub fn write_pid_with_mode(pid_file_path: &PathBuf, mode: u32) -> Result<(), PidError> {
+    let info = PidFileInfo::current();
+    debug!("Writing PID {} to {pid_file_path:?}...", info.pid);
 
     // 验证路径是否有效
     let path = pid_file_path
         .to_str()
         .ok_or(PidError::InvalidPidFilePath(pid_file_path.clone()))?;
+    let file_name = pid_file_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or(PidError::InvalidPidFilePath(pid_file_path.clone()))?;
+
+    // 创建父目录
+    let dir = match pid_file_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir).map_err(|e| PidError::CreatePidDir(dir.display().to_string(), e))?;
 
-    // 创建文件并写入当前进程ID
-    let pid_file = File::create(path).map_err(|_| PidError::CreatePidFile(path.to_string()))?;
-    let mut writer = BufWriter::new(pid_file);
-    writer
-        .write_all(pid.to_string().as_bytes())
-        .map_err(|_| PidError::WritePidFile(path.to_string()))?;
+    // 在同一目录下创建临时文件，保证重命名不会跨文件系统，文件名中的进程ID避免与其他进程冲突
+    let temp_path = dir.join(format!(".{file_name}.{}.tmp", info.pid));
+    let temp_file = create_temp_file(&temp_path, mode)
+        .map_err(|e| PidError::CreatePidFile(temp_path.display().to_string(), e))?;
+
+    // 写入内容并同步到磁盘后，重命名为PID文件
+    let result = write_temp_file(temp_file, mode, info.to_content().as_bytes())
+        .and_then(|_| fs::rename(&temp_path, pid_file_path))
+        .and_then(|_| File::open(dir)?.sync_all());
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(PidError::WritePidFile(path.to_string(), e));
+    }
 
     Ok(())
 }
 
+/// # 创建临时文件
+///
+/// 临时文件名可以被预测，PID 文件所在的目录可能被其他用户写入，因此以 `O_EXCL | O_NOFOLLOW` 创建，
+/// 不会跟随他人预先放置的符号链接去截断或修改其他文件。临时文件已存在时（如上次写入时崩溃残留），
+/// 删除后重试，删除符号链接只会删除链接本身。
+fn create_temp_file(temp_path: &Path, mode: u32) -> io::Result<File> {
+    let mut retries = 0;
+    loop {
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .mode(mode)
+            .open(temp_path);
+        match result {
+            Err(e)
+                if e.kind() == io::ErrorKind::AlreadyExists && retries < MAX_TEMP_FILE_RETRIES =>
+            {
+                debug!("Removing leftover temporary PID file {temp_path:?}...");
+                retries += 1;
+                fs::remove_file(temp_path)?;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// # 写入临时文件
+///
+/// 创建文件时指定的权限会受 umask 影响，这里重新设置一次权限，然后写入内容并同步到磁盘。
+fn write_temp_file(mut temp_file: File, mode: u32, content: &[u8]) -> io::Result<()> {
+    temp_file.set_permissions(Permissions::from_mode(mode))?;
+    temp_file.write_all(content)?;
+    temp_file.sync_all()
+}
+
 /// # 删除PID文件
 ///
 /// 删除指定路径的PID文件。如果文件不存在，则操作被视为成功。
@@ -141,7 +244,7 @@ pub fn write_pid(pid_file_path: &PathBuf) -> Result<(), PidError> {
 ///
 /// ## 错误类型
 /// - `InvalidPidFilePath`: 路径无效。
-/// - `DeletePidFileError`: 删除文件失败。
+/// - `DeletePidFile`: 删除文件失败。
 pub fn delete_pid_file(pid_file_path: &PathBuf) -> Result<(), PidError> {
     info!("Deleting PID file: {pid_file_path:?} ...");
 
@@ -151,9 +254,12 @@ pub fn delete_pid_file(pid_file_path: &PathBuf) -> Result<(), PidError> {
         .ok_or(PidError::InvalidPidFilePath(pid_file_path.clone()))?;
 
     // 删除文件（若文件不存在则视为成功）
-    std::fs::remove_file(pid_file_path).map_err(|_| PidError::DeletePidFile(path.to_string()))?;
-
-    Ok(())
+    match fs::remove_file(pid_file_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(PidError::DeletePidFile(path.to_string(), e))
+        }
+        _ => Ok(()),
+    }
 }
 
 /// # 删除PID文件（仅限当前进程创建的文件）
@@ -3,7 +3,8 @@
 //! 定义进程管理过程中可能出现的各种错误类型，用于统一处理进程检查、退出等操作中的异常情况。
 //! 该模块通过 `thiserror` 提供结构化的错误类型，方便上层业务逻辑进行模式匹配和错误传播。
 
-use crate::process::SignalError;
+use crate::process::{DaemonError, SignalError};
+use nix::errno::Errno;
 use thiserror::Error;
 
 /// # 进程相关错误枚举
@@ -15,17 +16,28 @@ pub enum ProcessError {
     /// 检查进程失败错误
     ///
     /// 当尝试检查进程状态（如是否存在、是否运行）时发生错误。
-    /// 可能的原因包括权限不足、进程 ID 无效或系统调用失败。
+    /// 可能的原因包括进程 ID 无效或系统调用失败。
+    ///
+    /// ## 参数
+    /// - `pid`: 检查的进程 ID。
+    /// - `errno`: 检查失败的系统错误码。
     ///
     /// ## 示例
     /// ```rust
-    /// use crate::process::ProcessError;
-    /// let error = ProcessError::CheckProcessError("Permission denied".to_string());
+    /// use nix::errno::Errno;
+    /// use wheel_rs::process::ProcessError;
+    /// let error = ProcessError::CheckProcess(1234, Errno::EINVAL);
     /// ```
-    #[error("Fail to check process: {0}")]
-    CheckProcess(String),
+    #[error("Fail to check process {0}")]
+    CheckProcess(u32, #[source] Errno),
 
-    #[error("{0}")]
+    /// 进程 ID 无效错误
+    ///
+    /// 当进程 ID 为 0 或超过 `i32::MAX` 时触发此错误，这些值在 `kill(2)` 中表示进程组或所有进程。
+    #[error("Invalid process id: {0}")]
+    InvalidPid(u32),
+
+    #[error(transparent)]
     Signal(#[from] SignalError),
 
     /// 进程退出等待超时
@@ -43,4 +55,10 @@ pub enum ProcessError {
     /// ```
     #[error("Process exit wait timeout: pid-{0}")]
     TerminateProcessTimeout(u32),
+
+    /// 守护进程化失败
+    ///
+    /// 包装了底层的 [DaemonError]。
+    #[error(transparent)]
+    Daemon(#[from] DaemonError),
 }
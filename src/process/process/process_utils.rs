@@ -5,7 +5,9 @@
 
 use crate::process::{send_signal_by_instruction, ProcessError};
 use libc::pid_t;
-use std::io;
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
 use std::time::Duration;
 use tokio::time::timeout;
 
@@ -93,30 +95,29 @@ async fn wait_for_process_exit(
 ///
 /// * `Ok(true)` - 进程存在。
 /// * `Ok(false)` - 进程不存在。
-/// * `Err(CheckProcessError)` - 检查过程中发生错误。
-///
-/// ## 安全性说明
-///
-/// 此函数使用 `unsafe` 块调用系统级API（`libc::kill`），但已被妥善封装以确保内存安全。
-/// 调用者无需担心未定义行为或内存泄漏问题。
+/// * `Err(InvalidPid)` - 进程ID为 0 或超过 `i32::MAX`。
+/// * `Err(CheckProcess)` - 检查过程中发生错误。
 ///
 /// ## 错误类型
-/// - `ESRCH`: 进程不存在。
-/// - `EPERM`: 进程存在但无权限访问。
-/// - 其他错误: 返回具体错误信息。
+/// - `ESRCH`: 进程不存在，返回 `Ok(false)`。
+/// - `EPERM`: 进程存在但无权限访问，返回 `Ok(true)`。
+/// - 其他错误: 返回包含系统错误码的 `CheckProcess`。
 pub fn check_process(pid: u32) -> Result<bool, ProcessError> {
-    unsafe {
-        let result = libc::kill(pid as pid_t, 0); // 信号 0
-        if result == 0 {
-            Ok(true) // 进程存在
-        } else {
-            let err = io::Error::last_os_error();
-            match err.raw_os_error() {
-                Some(libc::ESRCH) => Ok(false), // 进程不存在
-                Some(libc::EPERM) => Ok(true),  // 进程存在但无权限
-                Some(e) => Err(ProcessError::CheckProcess(e.to_string())),
-                None => Err(ProcessError::CheckProcess("Unknown error".to_string())),
-            }
-        }
+    if !is_valid_pid(pid) {
+        return Err(ProcessError::InvalidPid(pid));
+    }
+    // 发送信号 0，不会真正发送信号
+    match kill(Pid::from_raw(pid as pid_t), None) {
+        Ok(()) => Ok(true),             // 进程存在
+        Err(Errno::ESRCH) => Ok(false), // 进程不存在
+        Err(Errno::EPERM) => Ok(true),  // 进程存在但无权限
+        Err(e) => Err(ProcessError::CheckProcess(pid, e)),
     }
 }
+
+/// # 检查进程ID是否有效
+///
+/// 0 和超过 `i32::MAX` 的值转换为 `pid_t` 后在 `kill(2)` 中表示进程组或所有进程，不是有效的进程ID。
+pub(crate) fn is_valid_pid(pid: u32) -> bool {
+    pid > 0 && pid <= i32::MAX as u32
+}
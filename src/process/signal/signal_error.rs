@@ -2,6 +2,9 @@
 //!
 //! 定义信号发送过程中可能出现的各种错误类型，用于统一处理信号指令无效或发送失败等异常情况。
 //! 该模块通过 `thiserror` 提供结构化的错误类型，方便上层业务逻辑进行模式匹配和错误传播。
+use nix::errno::Errno;
+use nix::sys::signal::Signal;
+use std::io;
 use thiserror::Error;
 
 /// # 信号相关错误枚举
@@ -19,8 +22,8 @@ pub enum SignalError {
     ///
     /// ## 示例
     /// ```rust
-    /// use crate::signal::SignalError;
-    /// let error = SignalError::InvalidInstructionError("unknown_signal".to_string());
+    /// use wheel_rs::process::SignalError;
+    /// let error = SignalError::InvalidInstruction("unknown_signal".to_string());
     /// ```
     #[error("Invalid instruction: {0}")]
     InvalidInstruction(String),
@@ -28,30 +31,43 @@ pub enum SignalError {
     /// 发送信号失败错误
     ///
     /// 当尝试发送信号时因权限不足、目标进程不存在或其他系统级原因导致失败时触发此错误。
+    /// 可以通过底层的 [Errno] 区分目标进程不存在（`ESRCH`）和权限不足（`EPERM`）。
     ///
     /// ## 参数
-    /// - `reason`: 失败的具体原因描述。
+    /// - `signal`: 发送的信号。
+    /// - `pid`: 目标进程ID。
+    /// - `errno`: 发送失败的系统错误码。
     ///
     /// ## 示例
     /// ```rust
-    /// use crate::signal::SignalError;
-    /// let error = SignalError::SendSignalError("Permission denied".to_string());
+    /// use nix::errno::Errno;
+    /// use nix::sys::signal::Signal;
+    /// use wheel_rs::process::SignalError;
+    ///
+    /// let error = SignalError::SendSignal(Signal::SIGTERM, 1234, Errno::ESRCH);
+    /// assert!(matches!(error, SignalError::SendSignal(_, _, Errno::ESRCH)));
     /// ```
-    #[error("Fail to send signal: {0}")]
-    SendSignal(String),
+    #[error("Fail to send signal {0} to process {1}")]
+    SendSignal(Signal, u32, #[source] Errno),
 
     /// 注册信号处理函数失败错误
     ///
     /// 当尝试注册信号处理函数时因权限不足或其他系统级原因导致失败时触发此错误。
     ///
     /// ## 参数
-    /// - `signal`: 指示注册失败的是哪个信号
+    /// - `signal`: 指示注册失败的是哪个信号。
+    /// - `error`: 底层的 I/O 错误。
     ///
     /// ## 示例
     /// ```rust
-    /// use crate::signal::SignalError;
-    /// let error = SignalError::RegisterSignalHandlerError("SIGHUP".to_string());
+    /// use std::io;
+    /// use wheel_rs::process::SignalError;
+    ///
+    /// let error = SignalError::RegisterSignalHandler(
+    ///     "SIGHUP".to_string(),
+    ///     io::Error::from(io::ErrorKind::PermissionDenied),
+    /// );
     /// ```
-    #[error("Fail to register signal handler: {0}")]
-    RegisterSignalHandler(String),
+    #[error("Fail to register signal handler {0}")]
+    RegisterSignalHandler(String, #[source] io::Error),
 }
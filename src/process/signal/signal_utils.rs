@@ -37,10 +37,24 @@ use tokio::sync::broadcast::{Receiver, Sender};
 ///
 /// ## 错误处理
 ///
-/// 当指定的信号名称无效时，函数会返回 `InvalidInstructionError`。
-/// 若信号发送失败（如权限不足或进程不存在），则返回 `SendSignalError`。
+/// 当指定的信号名称无效时，函数会返回 `InvalidInstruction`。
+/// 若信号发送失败（如权限不足或进程不存在），则返回包含系统错误码的 `SendSignal`。
 pub fn send_signal_by_instruction(instruction: &str, pid: u32) -> Result<(), SignalError> {
     debug!("send signal by {instruction} instruction -> {pid}");
+    let signal = parse_signal_instruction(instruction)?;
+    kill(Pid::from_raw(pid as pid_t), signal).map_err(|e| SignalError::SendSignal(signal, pid, e))
+}
+
+/// # 解析信号指令
+///
+/// 将信号指令字符串（不区分大小写）转换为对应的系统信号，支持的指令见 [send_signal_by_instruction]。
+///
+/// ## 错误处理
+///
+/// 当指定的信号名称无效时，返回 `InvalidInstructionError`。
+pub fn parse_signal_instruction(
+    instruction: &str,
+) -> Result<nix::sys::signal::Signal, SignalError> {
     let instruction = instruction.to_lowercase();
     let signal = match instruction.as_str() {
         "hangup" => nix::sys::signal::Signal::SIGHUP,
@@ -51,8 +65,7 @@ pub fn send_signal_by_instruction(instruction: &str, pid: u32) -> Result<(), Sig
         "kill" => nix::sys::signal::Signal::SIGKILL,
         _ => Err(SignalError::InvalidInstruction(instruction.to_string()))?,
     };
-    kill(Pid::from_raw(pid as pid_t), signal)
-        .map_err(|_| SignalError::SendSignal(signal.to_string()))
+    Ok(signal)
 }
 
 /// # 异步监听系统信号
@@ -87,15 +100,15 @@ async fn watch_signal_internal(
 ) -> Result<(), SignalError> {
     debug!("watching signal...");
     let mut sighup_stream = signal(SignalKind::hangup())
-        .map_err(|_| SignalError::RegisterSignalHandler("SIGHUP".to_string()))?;
+        .map_err(|e| SignalError::RegisterSignalHandler("SIGHUP".to_string(), e))?;
     let mut sigcont_stream = signal(SignalKind::from_raw(18))
-        .map_err(|_| SignalError::RegisterSignalHandler("SIGCONT".to_string()))?;
+        .map_err(|e| SignalError::RegisterSignalHandler("SIGCONT".to_string(), e))?;
     let mut sigint_stream = signal(SignalKind::interrupt())
-        .map_err(|_| SignalError::RegisterSignalHandler("SIGINT".to_string()))?;
+        .map_err(|e| SignalError::RegisterSignalHandler("SIGINT".to_string(), e))?;
     let mut sigquit_stream = signal(SignalKind::quit())
-        .map_err(|_| SignalError::RegisterSignalHandler("SIGQUIT".to_string()))?;
+        .map_err(|e| SignalError::RegisterSignalHandler("SIGQUIT".to_string(), e))?;
     let mut sigterm_stream = signal(SignalKind::terminate())
-        .map_err(|_| SignalError::RegisterSignalHandler("SIGTERM".to_string()))?;
+        .map_err(|e| SignalError::RegisterSignalHandler("SIGTERM".to_string(), e))?;
 
     loop {
         tokio::select! {
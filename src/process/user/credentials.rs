@@ -0,0 +1,56 @@
+//! # 用户凭据模块
+//!
+//! 定义用户和用户组的标识方式 [IdOrName]，以及解析后的用户凭据 [Credentials]。
+
+use std::fmt::Display;
+
+/// # 用户或用户组的标识
+///
+/// 可以是数字ID，也可以是本地用户（组）数据库中的名称。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdOrName {
+    /// 数字ID
+    Id(u32),
+    /// 名称
+    Name(String),
+}
+
+impl From<u32> for IdOrName {
+    fn from(id: u32) -> Self {
+        IdOrName::Id(id)
+    }
+}
+
+impl From<&str> for IdOrName {
+    fn from(name: &str) -> Self {
+        IdOrName::Name(name.to_string())
+    }
+}
+
+impl From<String> for IdOrName {
+    fn from(name: String) -> Self {
+        IdOrName::Name(name)
+    }
+}
+
+impl Display for IdOrName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdOrName::Id(id) => write!(f, "{id}"),
+            IdOrName::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// # 用户凭据
+///
+/// 进程运行所使用的用户ID、用户组ID和附加用户组ID。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// 用户ID
+    pub uid: u32,
+    /// 用户组ID
+    pub gid: u32,
+    /// 附加用户组ID
+    pub groups: Vec<u32>,
+}
@@ -0,0 +1,8 @@
+//! # 用户模块
+//!
+//! 提供用户和用户组相关的功能，包括根据名称或ID解析用户凭据，以及降低当前进程的权限。
+//! 主要用于以 root 身份启动的守护进程以非特权账户运行。
+
+pub(super) mod credentials;
+pub(super) mod user_error;
+pub(super) mod user_utils;
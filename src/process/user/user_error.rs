@@ -0,0 +1,52 @@
+//! # 用户错误类型定义
+//!
+//! 定义解析用户凭据和降低进程权限过程中可能出现的各种错误类型。
+//! 该模块通过 `thiserror` 提供结构化的错误类型，方便上层业务逻辑进行模式匹配和错误传播。
+
+use nix::errno::Errno;
+use thiserror::Error;
+
+/// # 用户相关错误枚举
+///
+/// 包含解析用户凭据、降低进程权限过程中可能发生的各种错误类型。
+/// 通过 `thiserror` 宏实现，支持自动派生 `Display` 和 `Debug` 特性。
+#[derive(Error, Debug)]
+pub enum UserError {
+    /// 用户不存在错误
+    ///
+    /// 当按名称在本地用户数据库中找不到对应的用户时触发此错误。
+    #[error("User not found: {0}")]
+    UserNotFound(String),
+
+    /// 用户组不存在错误
+    ///
+    /// 当按名称在本地用户组数据库中找不到对应的用户组时触发此错误。
+    #[error("Group not found: {0}")]
+    GroupNotFound(String),
+
+    /// 查询用户信息失败错误
+    ///
+    /// 当读取本地用户数据库或用户所属的用户组发生系统错误时触发此错误，包含用户名称或ID和底层的 [Errno]。
+    #[error("Fail to lookup user {0}")]
+    LookupUser(String, #[source] Errno),
+
+    /// 查询用户组信息失败错误
+    ///
+    /// 当读取本地用户组数据库发生系统错误时触发此错误，包含用户组名称和底层的 [Errno]。
+    #[error("Fail to lookup group {0}")]
+    LookupGroup(String, #[source] Errno),
+
+    /// 缺少用户组错误
+    ///
+    /// 当按ID指定的用户在本地用户数据库中没有对应的账户，且未指定用户组时触发此错误，
+    /// 避免切换用户后仍保留原有的用户组权限。
+    #[error("Group is required for user without account: uid-{0}")]
+    MissingGroup(u32),
+
+    /// 降低进程权限失败错误
+    ///
+    /// 当设置附加用户组、用户组ID或用户ID失败时触发此错误，通常是因为当前进程没有足够的权限（`EPERM`）。
+    /// 包含要切换到的用户ID、用户组ID和底层的 [Errno]。
+    #[error("Fail to drop privileges to uid-{0}, gid-{1}")]
+    DropPrivileges(u32, u32, #[source] Errno),
+}
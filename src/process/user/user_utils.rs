@@ -0,0 +1,156 @@
+//! # 用户工具函数
+//!
+//! 提供根据名称或ID解析用户凭据，以及降低当前进程权限的功能。
+//! 用户和用户组名称从本地的 passwd/group 数据库中解析。
+
+use crate::process::{Credentials, IdOrName, UserError};
+use nix::errno::Errno;
+use nix::unistd::{Gid, Group, Uid, User, getgrouplist, setgid, setgroups, setuid};
+use std::ffi::CString;
+use tracing::{debug, info};
+
+/// # 解析用户ID
+///
+/// ## 错误处理
+///
+/// * 按名称找不到用户时，返回 [UserError::UserNotFound] 错误。
+/// * 查询用户数据库失败时，返回 [UserError::LookupUser] 错误。
+pub fn resolve_uid(user: &IdOrName) -> Result<u32, UserError> {
+    match user {
+        IdOrName::Id(uid) => Ok(*uid),
+        IdOrName::Name(name) => Ok(find_user(name)?.uid.as_raw()),
+    }
+}
+
+/// # 解析用户组ID
+///
+/// ## 错误处理
+///
+/// * 按名称找不到用户组时，返回 [UserError::GroupNotFound] 错误。
+/// * 查询用户组数据库失败时，返回 [UserError::LookupGroup] 错误。
+pub fn resolve_gid(group: &IdOrName) -> Result<u32, UserError> {
+    match group {
+        IdOrName::Id(gid) => Ok(*gid),
+        IdOrName::Name(name) => Group::from_name(name)
+            .map_err(|e| UserError::LookupGroup(name.clone(), e))?
+            .map(|group| group.gid.as_raw())
+            .ok_or_else(|| UserError::GroupNotFound(name.clone())),
+    }
+}
+
+/// # 解析用户凭据
+///
+/// 根据用户、用户组和附加用户组解析出完整的用户凭据，未指定的部分按照 `su` 的习惯补全：
+/// 用户组默认为用户的主用户组，附加用户组默认为用户所属的全部用户组。
+///
+/// ## 参数
+///
+/// * `user` - 用户
+/// * `group` - 用户组，为 `None` 时使用用户的主用户组
+/// * `groups` - 附加用户组，为 `None` 时使用用户所属的全部用户组；
+///   用户在用户数据库中没有对应的账户时为空
+///
+/// ## 错误处理
+///
+/// * 按名称找不到用户或用户组时，返回 [UserError::UserNotFound] 或 [UserError::GroupNotFound] 错误。
+/// * 按ID指定的用户没有对应的账户且未指定用户组时，返回 [UserError::MissingGroup] 错误。
+/// * 查询用户或用户组数据库失败时，返回 [UserError::LookupUser] 或 [UserError::LookupGroup] 错误。
+///
+/// ## 示例
+///
+/// ```
+/// use wheel_rs::process::{IdOrName, resolve_credentials};
+///
+/// let credentials = resolve_credentials(&IdOrName::from("root"), None, None).unwrap();
+/// assert_eq!(credentials.uid, 0);
+/// assert_eq!(credentials.gid, 0);
+///
+/// let credentials =
+///     resolve_credentials(&IdOrName::from(12345), Some(&IdOrName::from(12345)), Some(&[])).unwrap();
+/// assert!(credentials.groups.is_empty());
+/// ```
+pub fn resolve_credentials(
+    user: &IdOrName,
+    group: Option<&IdOrName>,
+    groups: Option<&[IdOrName]>,
+) -> Result<Credentials, UserError> {
+    let account = match user {
+        IdOrName::Id(uid) => User::from_uid(Uid::from_raw(*uid))
+            .map_err(|e| UserError::LookupUser(uid.to_string(), e))?,
+        IdOrName::Name(name) => Some(find_user(name)?),
+    };
+    let uid = match &account {
+        Some(account) => account.uid.as_raw(),
+        None => resolve_uid(user)?,
+    };
+    let gid = match (group, &account) {
+        (Some(group), _) => resolve_gid(group)?,
+        (None, Some(account)) => account.gid.as_raw(),
+        (None, None) => return Err(UserError::MissingGroup(uid)),
+    };
+    let groups = match (groups, &account) {
+        (Some(groups), _) => groups.iter().map(resolve_gid).collect::<Result<_, _>>()?,
+        (None, Some(account)) => {
+            // 用户数据库中的名称不会包含空字符
+            let name = CString::new(account.name.as_str())
+                .map_err(|_| UserError::LookupUser(account.name.clone(), Errno::EINVAL))?;
+            getgrouplist(&name, Gid::from_raw(gid))
+                .map_err(|e| UserError::LookupUser(account.name.clone(), e))?
+                .into_iter()
+                .map(Gid::as_raw)
+                .collect()
+        }
+        (None, None) => Vec::new(),
+    };
+    debug!("resolved credentials of {user}: uid-{uid}, gid-{gid}, groups-{groups:?}");
+    Ok(Credentials { uid, gid, groups })
+}
+
+/// # 降低当前进程的权限
+///
+/// 依次设置附加用户组、用户组ID和用户ID，切换后当前进程无法再恢复原有的权限。
+/// 通常在以 root 身份启动的守护进程完成初始化（如绑定特权端口）后调用。
+///
+/// ## 参数
+///
+/// * `credentials` - 要切换到的用户凭据，可以通过 [resolve_credentials] 解析得到
+///
+/// ## 错误处理
+///
+/// 设置失败时返回 [UserError::DropPrivileges] 错误，通常是因为当前进程没有足够的权限。
+pub fn drop_privileges(credentials: &Credentials) -> Result<(), UserError> {
+    let groups: Vec<Gid> = credentials
+        .groups
+        .iter()
+        .copied()
+        .map(Gid::from_raw)
+        .collect();
+    set_credentials(
+        &groups,
+        Gid::from_raw(credentials.gid),
+        Uid::from_raw(credentials.uid),
+    )
+    .map_err(|e| UserError::DropPrivileges(credentials.uid, credentials.gid, e))?;
+    info!(
+        "dropped privileges to uid-{}, gid-{}",
+        credentials.uid, credentials.gid
+    );
+    Ok(())
+}
+
+/// # 设置当前进程的凭据
+///
+/// 依次设置附加用户组、用户组ID和用户ID。设置用户ID后将失去设置用户组的权限，因此顺序不能改变。
+/// 不分配内存，可以在子进程 `exec` 之前的 `pre_exec` 回调中调用。
+pub(crate) fn set_credentials(groups: &[Gid], gid: Gid, uid: Uid) -> nix::Result<()> {
+    setgroups(groups)?;
+    setgid(gid)?;
+    setuid(uid)
+}
+
+/// # 按名称查找用户
+fn find_user(name: &str) -> Result<User, UserError> {
+    User::from_name(name)
+        .map_err(|e| UserError::LookupUser(name.to_string(), e))?
+        .ok_or_else(|| UserError::UserNotFound(name.to_string()))
+}